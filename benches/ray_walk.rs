@@ -0,0 +1,77 @@
+//! Times the ray traversals on large synthetic maps, with and without level of detail. That
+//! they report the same hits is checked by the unit tests in `traversal.rs`.
+//!
+//! Run with `cargo bench --bench ray_walk`.
+
+use std::time::{Duration, Instant};
+
+use height_map_test::{
+    main2, Endianness, Framebuffer, HeightMap, Quaternion, RenderOptions, Transform3, Traversal, Vec3, BLOCK_SIZE,
+};
+use noise::{Fbm, MultiFractal, NoiseFn, Perlin};
+
+const WIDTH: usize = 640;
+const HEIGHT: usize = 400;
+const FRAMES: usize = 8;
+
+/// Fractal terrain spanning the full 16-bit range, fed through the raw loader.
+fn make_height_map(size: usize) -> HeightMap {
+    let fbm = Fbm::<Perlin>::new(7).set_octaves(6);
+    let mut bytes = Vec::with_capacity(size * size * 2);
+    for z in 0..size {
+        for x in 0..size {
+            let h = fbm.get([x as f64 * 4.0 / 1024.0, z as f64 * 4.0 / 1024.0]);
+            let sample = ((h.clamp(-1.0, 1.0) * 0.5 + 0.5) * 65535.0) as u16;
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+    }
+    HeightMap::from_raw_u16(bytes.as_slice(), size, size, Endianness::Little).unwrap()
+}
+
+/// Cameras near one edge of the map looking across it at a few headings and heights.
+fn cameras(size: usize) -> Vec<Transform3<f64>> {
+    let half = 0.5 * size as f64 * BLOCK_SIZE;
+    let mut r = Vec::new();
+    for (yaw, height, pitch) in [(0.0, 900.0, 0.0), (20.0, 1200.0, -10.0), (-30.0, 700.0, 5.0), (10.0, 2000.0, -25.0)] {
+        let pos = Vec3::new(0.0, height, half * 0.9);
+        let orientation = Quaternion::from_yaw_pitch_roll(f64::to_radians(yaw), f64::to_radians(pitch), 0.0);
+        r.push(Transform3::new(pos, orientation));
+    }
+    r
+}
+
+fn time_frames(height_map: &HeightMap, cameras: &[Transform3<f64>], traversal: Traversal, pixel_error: f64) -> Duration {
+    let options = RenderOptions { traversal, pixel_error, ..RenderOptions::default() };
+    let mut screen = Framebuffer::new(WIDTH, HEIGHT);
+    let start = Instant::now();
+    for i in 0..FRAMES {
+        main2(height_map, &mut screen, &cameras[i % cameras.len()], &options);
+    }
+    start.elapsed() / FRAMES as u32
+}
+
+fn main() {
+    for size in [1024, 4096] {
+        let height_map = make_height_map(size);
+        let cameras = cameras(size);
+        let flat = time_frames(&height_map, &cameras, Traversal::FlatDda, 0.0);
+        for (traversal, pixel_error) in [
+            (Traversal::FlatDda, 0.0),
+            (Traversal::Dda, 0.0),
+            (Traversal::QuadTree, 0.0),
+            (Traversal::Dda, 1.0),
+            (Traversal::Dda, 4.0),
+            (Traversal::QuadTree, 1.0),
+        ] {
+            let frame = time_frames(&height_map, &cameras, traversal, pixel_error);
+            println!(
+                "{0}x{0} {1:?}, pixel error {2}: {3:.2} ms/frame ({4:.1}x flat)",
+                size,
+                traversal,
+                pixel_error,
+                frame.as_secs_f64() * 1000.0,
+                flat.as_secs_f64() / frame.as_secs_f64(),
+            );
+        }
+    }
+}
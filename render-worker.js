@@ -0,0 +1,24 @@
+// Renders one strip of columns per frame into a framebuffer shared with the page.
+import init, { Renderer, Terrain, Camera } from "./pkg/height_map_test.js";
+
+let renderer;
+let terrain;
+let shared;
+
+self.onmessage = async (event) => {
+  const message = event.data;
+  if (message.type === "init") {
+    await init();
+    renderer = new Renderer(message.width, message.height);
+    terrain = new Terrain(message.levels);
+    shared = new Uint32Array(message.buffer);
+    self.postMessage({ type: "ready" });
+  } else if (message.type === "frame") {
+    const camera = Camera.orbit(message.angle);
+    renderer.setCamera(camera);
+    camera.free();
+    renderer.renderColumns(terrain, message.x0, message.x1);
+    renderer.copyColumns(shared, message.x0, message.x1);
+    self.postMessage({ type: "done" });
+  }
+};
@@ -0,0 +1,110 @@
+// Renders frames once on the main thread and once as column strips from worker threads into a
+// SharedArrayBuffer, and checks the stitched strips are pixel-identical.
+//
+// Needs the web build in ./pkg (`npm run wasm`). Usage: node scripts/verify-strips.mjs [WORKERS]
+
+import { readFileSync } from "node:fs";
+import { Worker, isMainThread, parentPort, workerData } from "node:worker_threads";
+import { initSync, Renderer, Terrain, Camera } from "../pkg/height_map_test.js";
+
+const WIDTH = 160;
+const HEIGHT = 100;
+const LEVELS = 7;
+
+// Position, yaw, pitch and roll of each test frame; the last two exercise pitch and roll.
+const CAMERAS = [
+  [4000, 500, 0, 90, 0, 0],
+  [-3000, 700, 1500, -60, 0, 0],
+  [1000, 900, 2500, 20, -15, 0],
+  [-500, 600, -2000, 200, 10, 25],
+];
+
+const wasm = initSync({ module: readFileSync(new URL("../pkg/height_map_test_bg.wasm", import.meta.url)) });
+
+function setUp() {
+  const renderer = new Renderer(WIDTH, HEIGHT);
+  renderer.setLighting(-1, 0.6, 0.3, 0.35);
+  renderer.setExponentialFog(0.0001, 0, 170, 190, 210);
+  renderer.setSky(true);
+  renderer.setWater(20, true);
+  // A grid of half-transparent sprites, so some straddle strip edges and hide behind hills.
+  const image = renderer.addSpriteImage(2, 2, new Uint8Array([
+    255, 0, 0, 255, 0, 255, 0, 128,
+    0, 0, 255, 255, 255, 255, 0, 64,
+  ]));
+  for (let x = -4000; x <= 4000; x += 500) {
+    for (let z = -4000; z <= 4000; z += 500) {
+      renderer.addSprite(x, 100, z, 200, image);
+    }
+  }
+  return renderer;
+}
+
+function setCamera(renderer, index) {
+  const camera = new Camera(...CAMERAS[index]);
+  renderer.setCamera(camera);
+  camera.free();
+}
+
+if (!isMainThread) {
+  const { x0, x1, buffer } = workerData;
+  const renderer = setUp();
+  const terrain = new Terrain(LEVELS);
+  const shared = new Uint32Array(buffer);
+  parentPort.on("message", (index) => {
+    setCamera(renderer, index);
+    renderer.renderColumns(terrain, x0, x1);
+    renderer.copyColumns(shared, x0, x1);
+    parentPort.postMessage("done");
+  });
+  parentPort.postMessage("ready");
+} else {
+  const count = Number(process.argv[2] ?? 4);
+  const buffer = new SharedArrayBuffer(4 * WIDTH * HEIGHT);
+  const workers = [];
+  for (let i = 0; i < count; ++i) {
+    const x0 = Math.floor(WIDTH * i / count);
+    const x1 = Math.floor(WIDTH * (i + 1) / count);
+    workers.push(new Worker(new URL(import.meta.url), { workerData: { x0, x1, buffer } }));
+  }
+  const next = (worker) => new Promise((resolve, reject) => {
+    worker.once("message", resolve);
+    worker.once("error", reject);
+  });
+  await Promise.all(workers.map(next));
+
+  const renderer = setUp();
+  const terrain = new Terrain(LEVELS);
+  let failures = 0;
+  for (let index = 0; index < CAMERAS.length; ++index) {
+    new Uint32Array(buffer).fill(0);
+    const strips = workers.map((worker) => {
+      const done = next(worker);
+      worker.postMessage(index);
+      return done;
+    });
+    setCamera(renderer, index);
+    renderer.render(terrain);
+    await Promise.all(strips);
+    const full = new Uint32Array(wasm.memory.buffer, renderer.pixelsPtr(), renderer.pixelsLen());
+    const stitched = new Uint32Array(buffer);
+    let mismatches = 0;
+    for (let y = 0; y < HEIGHT; ++y) {
+      for (let x = 0; x < WIDTH; ++x) {
+        if (full[y * renderer.stride() + x] !== stitched[y * WIDTH + x]) {
+          ++mismatches;
+        }
+      }
+    }
+    console.log(`camera ${index}: ${mismatches === 0 ? "identical" : `${mismatches} pixels differ`}`);
+    if (mismatches !== 0) {
+      ++failures;
+    }
+  }
+  await Promise.all(workers.map((worker) => worker.terminate()));
+  if (failures !== 0) {
+    console.error(`${failures} of ${CAMERAS.length} frames differ with ${count} workers`);
+    process.exit(1);
+  }
+  console.log(`all ${CAMERAS.length} frames identical with ${count} workers`);
+}
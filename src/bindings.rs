@@ -0,0 +1,611 @@
+use js_sys::Uint32Array;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    init_panic_hook, main2, orbit_camera_space, pick, render_columns, Brush, BrushOp, BrushShape, CloudConfig, Endianness,
+    Falloff, FogConfig, FogMode, Framebuffer, HeightFilter, HeightMap, LightingConfig, Quaternion, RenderOptions, ShadowMap,
+    SkyConfig, Sprite, SpriteImage, Transform3, Vec3, WaterConfig,
+};
+
+/// Most levels `new Terrain` generates: a 2048x2048 map, whose pyramid, normals and generation
+/// buffers fit comfortably in wasm32's 4 GiB. `MAX_LEVELS` maps would not.
+const MAX_GENERATED_LEVELS: usize = 12;
+
+/// Largest accepted framebuffer width or height, in pixels.
+const MAX_SCREEN_SIDE: usize = 16384;
+
+/// Saturates rather than wrapping, which a large limit would do on wasm32.
+fn megabytes_to_bytes(megabytes: usize) -> usize {
+    megabytes.saturating_mul(1 << 20)
+}
+
+fn check_finite(name: &str, value: f64) -> Result<(), JsError> {
+    if !value.is_finite() {
+        return Err(JsError::new(&format!("{} must be finite, got {}", name, value)));
+    }
+    Ok(())
+}
+
+fn check_sprite_position(x: f64, y: f64, z: f64) -> Result<(), JsError> {
+    if !(x.is_finite() && y.is_finite() && z.is_finite()) {
+        return Err(JsError::new(&format!("sprite position must be finite, got ({}, {}, {})", x, y, z)));
+    }
+    Ok(())
+}
+
+fn check_screen_size(width: usize, height: usize) -> Result<(), JsError> {
+    if width == 0 || height == 0 || width > MAX_SCREEN_SIDE || height > MAX_SCREEN_SIDE {
+        return Err(JsError::new(&format!("screen size must be between 1 and {} pixels a side, got {}x{}", MAX_SCREEN_SIDE, width, height)));
+    }
+    Ok(())
+}
+
+/// A height map owned by JS. Freed by `free()` or when the JS object is garbage collected.
+#[wasm_bindgen]
+pub struct Terrain {
+    height_map: HeightMap,
+}
+
+impl Terrain {
+    pub fn height_map(&self) -> &HeightMap {
+        &self.height_map
+    }
+}
+
+#[wasm_bindgen]
+impl Terrain {
+    /// Generates the default planet with `num_levels` quadtree levels, from 1 to 12.
+    #[wasm_bindgen(constructor)]
+    pub fn new(num_levels: usize) -> Result<Terrain, JsError> {
+        init_panic_hook();
+        if num_levels == 0 || num_levels > MAX_GENERATED_LEVELS {
+            return Err(JsError::new(&format!("num_levels must be between 1 and {}, got {}", MAX_GENERATED_LEVELS, num_levels)));
+        }
+        Ok(Terrain { height_map: HeightMap::new(num_levels) })
+    }
+
+    #[wasm_bindgen(js_name = fromPgm)]
+    pub fn from_pgm(bytes: &[u8]) -> Result<Terrain, JsError> {
+        let height_map = HeightMap::from_pgm(bytes).map_err(|err| JsError::new(&err.to_string()))?;
+        Ok(Terrain { height_map })
+    }
+
+    #[wasm_bindgen(js_name = fromPng)]
+    pub fn from_png(bytes: &[u8]) -> Result<Terrain, JsError> {
+        let height_map = HeightMap::from_png_gray16(bytes).map_err(|err| JsError::new(&err.to_string()))?;
+        Ok(Terrain { height_map })
+    }
+
+    #[wasm_bindgen(js_name = fromRawU16)]
+    pub fn from_raw_u16(bytes: &[u8], width: usize, height: usize, little_endian: bool) -> Result<Terrain, JsError> {
+        let endianness = if little_endian { Endianness::Little } else { Endianness::Big };
+        let height_map = HeightMap::from_raw_u16(bytes, width, height, endianness).map_err(|err| JsError::new(&err.to_string()))?;
+        Ok(Terrain { height_map })
+    }
+
+    /// Width (and height) in cells of the finest level.
+    pub fn size(&self) -> usize {
+        self.height_map.size()
+    }
+
+    /// Stored height of a finest-level cell.
+    pub fn read(&self, x: usize, z: usize) -> Result<f64, JsError> {
+        let size = self.height_map.size();
+        if x >= size || z >= size {
+            return Err(JsError::new(&format!("cell ({}, {}) is outside the {}x{} map", x, z, size, size)));
+        }
+        Ok(self.height_map.read(self.height_map.num_levels()-1, x, z))
+    }
+
+    /// Overwrites a `width` by `height` block of finest-level cells from (`x`, `z`), given row
+    /// by row in stored units. Shadows catch up on the next `updateShadows`.
+    #[wasm_bindgen(js_name = writeRegion)]
+    pub fn write_region(&mut self, x: usize, z: usize, width: usize, height: usize, heights: &[f64]) -> Result<(), JsError> {
+        let size = self.height_map.size();
+        if width.checked_mul(height) != Some(heights.len()) {
+            return Err(JsError::new(&format!("expected {}x{} heights, got {}", width, height, heights.len())));
+        }
+        if x.checked_add(width).is_none_or(|x1| x1 > size) || z.checked_add(height).is_none_or(|z1| z1 > size) {
+            return Err(JsError::new(&format!("region {}x{} at ({}, {}) is outside the {}x{} map", width, height, x, z, size, size)));
+        }
+        self.height_map.write_region(x, z, width, height, heights);
+        Ok(())
+    }
+
+    /// Applies one dab of `brush` centred on world position (`x`, `z`), e.g. from `pick`.
+    #[wasm_bindgen(js_name = applyBrush)]
+    pub fn apply_brush(&mut self, brush: &BrushController, x: f64, z: f64) {
+        brush.brush.apply(&mut self.height_map, x, z);
+    }
+
+    /// Groups every edit until `endStroke` into one undo step, e.g. while the mouse is down.
+    #[wasm_bindgen(js_name = beginStroke)]
+    pub fn begin_stroke(&mut self) {
+        self.height_map.begin_stroke();
+    }
+
+    #[wasm_bindgen(js_name = endStroke)]
+    pub fn end_stroke(&mut self) {
+        self.height_map.end_stroke();
+    }
+
+    /// Reverts the newest edit or stroke; false if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        self.height_map.undo()
+    }
+
+    pub fn redo(&mut self) -> bool {
+        self.height_map.redo()
+    }
+
+    #[wasm_bindgen(js_name = canUndo)]
+    pub fn can_undo(&self) -> bool {
+        self.height_map.can_undo()
+    }
+
+    #[wasm_bindgen(js_name = canRedo)]
+    pub fn can_redo(&self) -> bool {
+        self.height_map.can_redo()
+    }
+
+    /// Keeps at most `max_steps` undo steps and about `max_megabytes` of saved heights.
+    #[wasm_bindgen(js_name = setHistoryLimit)]
+    pub fn set_history_limit(&mut self, max_steps: usize, max_megabytes: usize) {
+        self.height_map.set_history_limit(max_steps, megabytes_to_bytes(max_megabytes));
+    }
+}
+
+/// A sculpting brush as seen from JS; see `Terrain.applyBrush`.
+#[wasm_bindgen(js_name = Brush)]
+pub struct BrushController {
+    brush: Brush,
+}
+
+#[wasm_bindgen(js_class = Brush)]
+impl BrushController {
+    /// `shape` is "circle" or "square" and `falloff` "linear", "smooth" or "gaussian". The brush
+    /// raises terrain until `setOperation` says otherwise.
+    #[wasm_bindgen(constructor)]
+    pub fn new(shape: &str, falloff: &str, radius: f64, strength: f64) -> Result<BrushController, JsError> {
+        let shape = match shape {
+            "circle" => BrushShape::Circle,
+            "square" => BrushShape::Square,
+            _ => return Err(JsError::new(&format!("unknown brush shape '{}'", shape))),
+        };
+        let falloff = match falloff {
+            "linear" => Falloff::Linear,
+            "smooth" => Falloff::Smooth,
+            "gaussian" => Falloff::Gaussian,
+            _ => return Err(JsError::new(&format!("unknown falloff '{}'", falloff))),
+        };
+        check_finite("radius", radius)?;
+        check_finite("strength", strength)?;
+        Ok(BrushController {
+            brush: Brush { shape, radius, falloff, strength, op: BrushOp::Raise },
+        })
+    }
+
+    /// One of "raise", "lower", "smooth", "flatten" (to world height `value`) or "noise" (with
+    /// features `value` world units across, which must be positive).
+    #[wasm_bindgen(js_name = setOperation)]
+    pub fn set_operation(&mut self, op: &str, value: f64) -> Result<(), JsError> {
+        self.brush.op = match op {
+            "raise" => BrushOp::Raise,
+            "lower" => BrushOp::Lower,
+            "smooth" => BrushOp::Smooth,
+            "flatten" => {
+                check_finite("flatten height", value)?;
+                BrushOp::Flatten { height: value }
+            }
+            "noise" => {
+                if !(value.is_finite() && value > 0.0) {
+                    return Err(JsError::new(&format!("noise scale must be positive and finite, got {}", value)));
+                }
+                BrushOp::Noise { seed: 0, scale: value }
+            }
+            _ => return Err(JsError::new(&format!("unknown brush operation '{}'", op))),
+        };
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = setRadius)]
+    pub fn set_radius(&mut self, radius: f64) -> Result<(), JsError> {
+        check_finite("radius", radius)?;
+        self.brush.radius = radius;
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = setStrength)]
+    pub fn set_strength(&mut self, strength: f64) -> Result<(), JsError> {
+        check_finite("strength", strength)?;
+        self.brush.strength = strength;
+        Ok(())
+    }
+}
+
+/// Camera position in world units and orientation in degrees, as seen from JS.
+#[wasm_bindgen(js_name = Camera)]
+pub struct CameraController {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub yaw: f64,
+    pub pitch: f64,
+    pub roll: f64,
+}
+
+impl CameraController {
+    pub fn space(&self) -> Transform3<f64> {
+        Transform3::new(
+            Vec3::new(self.x, self.y, self.z),
+            Quaternion::from_yaw_pitch_roll(self.yaw.to_radians(), self.pitch.to_radians(), self.roll.to_radians()),
+        )
+    }
+}
+
+#[wasm_bindgen(js_class = Camera)]
+impl CameraController {
+    #[wasm_bindgen(constructor)]
+    pub fn new(x: f64, y: f64, z: f64, yaw: f64, pitch: f64, roll: f64) -> CameraController {
+        CameraController { x, y, z, yaw, pitch, roll }
+    }
+
+    /// The camera path of the original demo, orbiting the map centre. `angle` is in degrees.
+    pub fn orbit(angle: f64) -> CameraController {
+        let space = orbit_camera_space(angle);
+        CameraController {
+            x: space.origin.x,
+            y: space.origin.y,
+            z: space.origin.z,
+            yaw: angle + 90.0,
+            pitch: 0.0,
+            roll: 0.0,
+        }
+    }
+}
+
+/// The terrain under a pixel, as seen from JS: a world position, the finest-level cell,
+/// the terrain height there and the distance from the camera.
+#[wasm_bindgen(js_name = PickHit)]
+pub struct PickResult {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    #[wasm_bindgen(js_name = cellX)]
+    pub cell_x: usize,
+    #[wasm_bindgen(js_name = cellZ)]
+    pub cell_z: usize,
+    pub height: f64,
+    pub distance: f64,
+}
+
+/// Owns the framebuffer and render settings. JS reads the pixels through `pixelsPtr()`,
+/// which stays valid until the next `resize()` or `free()`.
+#[wasm_bindgen]
+pub struct Renderer {
+    screen: Framebuffer,
+    camera_space: Transform3<f64>,
+    options: RenderOptions,
+}
+
+impl Renderer {
+    pub fn screen(&self) -> &Framebuffer {
+        &self.screen
+    }
+}
+
+#[wasm_bindgen]
+impl Renderer {
+    #[wasm_bindgen(constructor)]
+    pub fn new(width: usize, height: usize) -> Result<Renderer, JsError> {
+        init_panic_hook();
+        check_screen_size(width, height)?;
+        Ok(Renderer {
+            screen: Framebuffer::new(width, height),
+            camera_space: orbit_camera_space(0.0),
+            options: RenderOptions::default(),
+        })
+    }
+
+    pub fn resize(&mut self, width: usize, height: usize) -> Result<(), JsError> {
+        check_screen_size(width, height)?;
+        let has_depth = self.screen.has_depth();
+        self.screen = Framebuffer::new(width, height);
+        if has_depth {
+            self.screen.enable_depth();
+        }
+        Ok(())
+    }
+
+    pub fn width(&self) -> usize {
+        self.screen.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.screen.height()
+    }
+
+    pub fn stride(&self) -> usize {
+        self.screen.stride()
+    }
+
+    #[wasm_bindgen(js_name = setCamera)]
+    pub fn set_camera(&mut self, camera: &CameraController) {
+        self.camera_space = camera.space();
+    }
+
+    /// Accepts "nearest", "bilinear", "bicubic", or nothing for flat cells.
+    #[wasm_bindgen(js_name = setHeightFilter)]
+    pub fn set_height_filter(&mut self, filter: Option<String>) -> Result<(), JsError> {
+        self.options.height_filter = match filter.as_deref() {
+            None => None,
+            Some("nearest") => Some(HeightFilter::Nearest),
+            Some("bilinear") => Some(HeightFilter::Bilinear),
+            Some("bicubic") => Some(HeightFilter::Bicubic),
+            Some(name) => return Err(JsError::new(&format!("unknown height filter '{}'", name))),
+        };
+        Ok(())
+    }
+
+    /// Exponential distance fog; `height_falloff` thins it with altitude (0 for uniform fog).
+    #[wasm_bindgen(js_name = setExponentialFog)]
+    pub fn set_exponential_fog(&mut self, density: f64, height_falloff: f64, r: u8, g: u8, b: u8) {
+        self.options.fog = Some(FogConfig {
+            mode: FogMode::Exponential { density },
+            colour: [r, g, b],
+            height_falloff,
+            aerial_perspective: None,
+        });
+    }
+
+    #[wasm_bindgen(js_name = setLinearFog)]
+    pub fn set_linear_fog(&mut self, start: f64, end: f64, height_falloff: f64, r: u8, g: u8, b: u8) {
+        self.options.fog = Some(FogConfig {
+            mode: FogMode::Linear { start, end },
+            colour: [r, g, b],
+            height_falloff,
+            aerial_perspective: None,
+        });
+    }
+
+    #[wasm_bindgen(js_name = clearFog)]
+    pub fn clear_fog(&mut self) {
+        self.options.fog = None;
+    }
+
+    /// Lambert shading from a sun in direction (`x`, `y`, `z`), which need not be normalised.
+    /// Cast shadows, if enabled, are marked stale and catch up through `updateShadows`.
+    #[wasm_bindgen(js_name = setLighting)]
+    pub fn set_lighting(&mut self, x: f64, y: f64, z: f64, ambient: f64) {
+        let sun_direction = Vec3::new(x, y, z).normalize();
+        self.options.lighting = Some(LightingConfig {
+            sun_direction,
+            ambient,
+        });
+        if let Some(shadow_map) = &mut self.options.shadow_map {
+            shadow_map.set_sun_direction(sun_direction);
+        }
+    }
+
+    /// Casts shadows over `terrain` from the current lighting sun (or the default sun).
+    /// Shadowed cells are darkened to `shadow_light`.
+    #[wasm_bindgen(js_name = enableShadows)]
+    pub fn enable_shadows(&mut self, terrain: &Terrain, shadow_light: f32) {
+        let sun_direction = match &self.options.lighting {
+            Some(lighting) => lighting.sun_direction,
+            None => LightingConfig::default().sun_direction,
+        };
+        self.options.shadow_map = Some(ShadowMap::new(&terrain.height_map, sun_direction, shadow_light));
+    }
+
+    #[wasm_bindgen(js_name = disableShadows)]
+    pub fn disable_shadows(&mut self) {
+        self.options.shadow_map = None;
+    }
+
+    /// Recomputes up to `max_rows` stale shadow rows; returns true once shadows are up to date.
+    /// Shadows enabled for another terrain start over for `terrain`.
+    #[wasm_bindgen(js_name = updateShadows)]
+    pub fn update_shadows(&mut self, terrain: &Terrain, max_rows: usize) -> bool {
+        match &mut self.options.shadow_map {
+            Some(shadow_map) => shadow_map.update(&terrain.height_map, max_rows),
+            None => true,
+        }
+    }
+
+    #[wasm_bindgen(js_name = clearLighting)]
+    pub fn clear_lighting(&mut self) {
+        self.options.lighting = None;
+    }
+
+    /// Default sky gradient and sun disc, optionally with procedural clouds.
+    #[wasm_bindgen(js_name = setSky)]
+    pub fn set_sky(&mut self, clouds: bool) {
+        self.options.sky = Some(SkyConfig {
+            clouds: if clouds { Some(CloudConfig::default()) } else { None },
+            ..SkyConfig::default()
+        });
+    }
+
+    #[wasm_bindgen(js_name = clearSky)]
+    pub fn clear_sky(&mut self) {
+        self.options.sky = None;
+    }
+
+    /// Water over terrain below `sea_level` (world units), with or without shoreline foam.
+    #[wasm_bindgen(js_name = setWater)]
+    pub fn set_water(&mut self, sea_level: f64, foam: bool) {
+        let mut water = WaterConfig {
+            sea_level,
+            ..WaterConfig::default()
+        };
+        if !foam {
+            water.foam = None;
+        }
+        self.options.water = Some(water);
+    }
+
+    #[wasm_bindgen(js_name = clearWater)]
+    pub fn clear_water(&mut self) {
+        self.options.water = None;
+    }
+
+    /// Walks far terrain at coarser, averaged levels once a node is at most `pixels` wide on
+    /// screen; 0 renders everything at full resolution.
+    #[wasm_bindgen(js_name = setPixelError)]
+    pub fn set_pixel_error(&mut self, pixels: f64) {
+        self.options.pixel_error = pixels;
+    }
+
+    /// Adds an RGBA image (`width * height * 4` bytes, rows from the top) for sprites to use
+    /// and returns its index.
+    #[wasm_bindgen(js_name = addSpriteImage)]
+    pub fn add_sprite_image(&mut self, width: usize, height: usize, rgba: &[u8]) -> Result<usize, JsError> {
+        if width == 0 || height == 0 {
+            return Err(JsError::new(&format!("sprite images must not be empty, got {}x{}", width, height)));
+        }
+        if width.checked_mul(height).and_then(|n| n.checked_mul(4)) != Some(rgba.len()) {
+            return Err(JsError::new(&format!("expected {}x{}x4 bytes of RGBA, got {}", width, height, rgba.len())));
+        }
+        Ok(self.options.sprites.add_image(SpriteImage::from_rgba(width, height, rgba)))
+    }
+
+    /// Adds a billboard `size` world units tall standing on (`x`, `y`, `z`) and returns its index.
+    #[wasm_bindgen(js_name = addSprite)]
+    pub fn add_sprite(&mut self, x: f64, y: f64, z: f64, size: f64, image: usize) -> Result<usize, JsError> {
+        if image >= self.options.sprites.num_images() {
+            return Err(JsError::new(&format!("sprite image {} has not been added", image)));
+        }
+        check_sprite_position(x, y, z)?;
+        if !(size.is_finite() && size > 0.0) {
+            return Err(JsError::new(&format!("sprite size must be positive and finite, got {}", size)));
+        }
+        Ok(self.options.sprites.add(Sprite {
+            position: Vec3::new(x, y, z),
+            size,
+            image,
+        }))
+    }
+
+    /// Moves the sprite returned by `addSprite`.
+    #[wasm_bindgen(js_name = moveSprite)]
+    pub fn move_sprite(&mut self, index: usize, x: f64, y: f64, z: f64) -> Result<(), JsError> {
+        check_sprite_position(x, y, z)?;
+        match self.options.sprites.sprites.get_mut(index) {
+            Some(sprite) => {
+                sprite.position = Vec3::new(x, y, z);
+                Ok(())
+            }
+            None => Err(JsError::new(&format!("no sprite {}", index))),
+        }
+    }
+
+    /// Removes every sprite; images stay available.
+    #[wasm_bindgen(js_name = clearSprites)]
+    pub fn clear_sprites(&mut self) {
+        self.options.sprites.clear();
+    }
+
+    /// Shadows enabled for another terrain are left out until `updateShadows` has
+    /// caught up with `terrain`.
+    pub fn render(&mut self, terrain: &Terrain) {
+        main2(&terrain.height_map, &mut self.screen, &self.camera_space, &self.options);
+    }
+
+    /// Renders only columns `x0..x1` of the frame; see `copyColumns` for sharing them.
+    #[wasm_bindgen(js_name = renderColumns)]
+    pub fn render_columns(&mut self, terrain: &Terrain, x0: usize, x1: usize) {
+        render_columns(&terrain.height_map, &mut self.screen, &self.camera_space, &self.options, x0..x1);
+    }
+
+    /// Copies columns `x0..x1` into `target`, a `width * height` row-major view such as one
+    /// over a `SharedArrayBuffer` that several workers each fill with their own strip.
+    #[wasm_bindgen(js_name = copyColumns)]
+    pub fn copy_columns(&self, target: &Uint32Array, x0: usize, x1: usize) -> Result<(), JsError> {
+        let width = self.screen.width();
+        let x1 = x1.min(width);
+        if target.length() as usize != width * self.screen.height() {
+            return Err(JsError::new("target must hold exactly width * height pixels"));
+        }
+        if x0 >= x1 {
+            return Ok(());
+        }
+        for y in 0..self.screen.height() {
+            let offset = self.screen.offset(x0, y);
+            let row = &self.screen.pixels()[offset..offset + (x1 - x0)];
+            let start = (y * width + x0) as u32;
+            target.subarray(start, start + row.len() as u32).copy_from(row);
+        }
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = pixelsPtr)]
+    pub fn pixels_ptr(&self) -> *const u32 {
+        self.screen.pixels().as_ptr()
+    }
+
+    /// Records the distance to the surface under each pixel on every render, readable through
+    /// `depthPtr()` with the same layout as the pixels.
+    #[wasm_bindgen(js_name = enableDepth)]
+    pub fn enable_depth(&mut self) {
+        self.screen.enable_depth();
+    }
+
+    #[wasm_bindgen(js_name = disableDepth)]
+    pub fn disable_depth(&mut self) {
+        self.screen.disable_depth();
+    }
+
+    /// `f32` distances, `Infinity` for sky; null unless depth is enabled. Valid until the next
+    /// `resize()`, `disableDepth()` or `free()`.
+    #[wasm_bindgen(js_name = depthPtr)]
+    pub fn depth_ptr(&self) -> *const f32 {
+        match self.screen.depth() {
+            Some(depth) => depth.as_ptr(),
+            None => std::ptr::null(),
+        }
+    }
+
+    /// Distance to the surface at pixel (`x`, `y`): `Infinity` for sky or without depth, and
+    /// `NaN` outside the screen.
+    #[wasm_bindgen(js_name = depthAt)]
+    pub fn depth_at(&self, x: usize, y: usize) -> f32 {
+        if x >= self.screen.width() || y >= self.screen.height() {
+            return f32::NAN;
+        }
+        self.screen.get_depth(x, y)
+    }
+
+    /// The terrain drawn at pixel (`x`, `y`) with the current camera and settings, or
+    /// undefined for sky. Works without rendering first.
+    pub fn pick(&self, terrain: &Terrain, x: usize, y: usize) -> Option<PickResult> {
+        let hit = pick(&terrain.height_map, self.screen.width(), self.screen.height(), &self.camera_space, &self.options, x, y)?;
+        Some(PickResult {
+            x: hit.world_pos.x,
+            y: hit.world_pos.y,
+            z: hit.world_pos.z,
+            cell_x: hit.cell.0,
+            cell_z: hit.cell.1,
+            height: hit.height,
+            distance: hit.distance,
+        })
+    }
+
+    /// Number of `u32` pixels behind `pixelsPtr()`, including any stride padding.
+    #[wasm_bindgen(js_name = pixelsLen)]
+    pub fn pixels_len(&self) -> usize {
+        self.screen.pixels().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_limits_saturate() {
+        assert_eq!(megabytes_to_bytes(3), 3 << 20);
+        assert_eq!(megabytes_to_bytes(usize::MAX), usize::MAX);
+        assert_eq!(megabytes_to_bytes(usize::MAX >> 19), usize::MAX);
+    }
+}
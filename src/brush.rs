@@ -0,0 +1,233 @@
+use noise::{NoiseFn, Perlin};
+
+use crate::{HeightMap, BLOCK_SIZE, HEIGHT_SCALE};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BrushShape {
+    Circle,
+    /// Axis-aligned, `radius` from the centre to each side.
+    Square,
+}
+
+/// How a brush's effect fades from full at its centre to nothing at its edge.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Falloff {
+    Linear,
+    /// Smoothstep, flat at both the centre and the edge.
+    Smooth,
+    /// A bell curve with the edge three standard deviations out.
+    Gaussian,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BrushOp {
+    /// Adds `strength` world units at the centre.
+    Raise,
+    /// Subtracts `strength` world units at the centre.
+    Lower,
+    /// Moves each cell towards the mean of its 3x3 neighbourhood by `strength` (`0.0..=1.0`).
+    Smooth,
+    /// Moves each cell towards world-space `height` by `strength` (`0.0..=1.0`).
+    Flatten { height: f64 },
+    /// Adds Perlin noise of amplitude `strength` world units and feature size `scale` world
+    /// units. The pattern is fixed in the world, so repeated dabs build up coherently.
+    Noise { seed: u32, scale: f64 },
+}
+
+/// A sculpting brush applied to a `HeightMap` in world coordinates. Each `apply` is one dab;
+/// strokes are made of many.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Brush {
+    pub shape: BrushShape,
+    /// World-space radius.
+    pub radius: f64,
+    pub falloff: Falloff,
+    /// Amount of one dab at the centre; see `BrushOp` for its unit.
+    pub strength: f64,
+    pub op: BrushOp,
+}
+
+impl Brush {
+    /// Whether every setting is usable: finite radius, strength and flatten height, and a
+    /// positive, finite noise scale. `apply` does nothing with an invalid brush.
+    pub fn is_valid(&self) -> bool {
+        let op_valid = match self.op {
+            BrushOp::Raise | BrushOp::Lower | BrushOp::Smooth => true,
+            BrushOp::Flatten { height } => height.is_finite(),
+            BrushOp::Noise { scale, .. } => scale.is_finite() && scale > 0.0,
+        };
+        op_valid && self.radius.is_finite() && self.strength.is_finite()
+    }
+
+    /// Weight in `0.0..=1.0` of a point offset (`dx`, `dz`) world units from the centre.
+    pub fn weight(&self, dx: f64, dz: f64) -> f64 {
+        if self.radius <= 0.0 {
+            return 0.0;
+        }
+        let distance = match self.shape {
+            BrushShape::Circle => dx.hypot(dz),
+            BrushShape::Square => dx.abs().max(dz.abs()),
+        };
+        let t = distance / self.radius;
+        if t >= 1.0 {
+            return 0.0;
+        }
+        match self.falloff {
+            Falloff::Linear => 1.0 - t,
+            Falloff::Smooth => 1.0 - t * t * (3.0 - 2.0 * t),
+            Falloff::Gaussian => (-4.5 * t * t).exp(),
+        }
+    }
+
+    /// Applies one dab centred on world position (`x`, `z`) to the finest cells whose centres
+    /// it covers, writing them back as one region.
+    pub fn apply(&self, height_map: &mut HeightMap, x: f64, z: f64) {
+        if !self.is_valid() {
+            return;
+        }
+        let size = height_map.size();
+        let fine = height_map.num_levels()-1;
+        let half_size2 = 0.5 * size as f64 * BLOCK_SIZE;
+        // Cells whose centres may fall inside the brush, clamped to the map.
+        let cell_range = |centre: f64| {
+            let lo = ((centre - self.radius + half_size2) / BLOCK_SIZE - 0.5).ceil().max(0.0);
+            let hi = ((centre + self.radius + half_size2) / BLOCK_SIZE - 0.5).floor() + 1.0;
+            (lo.min(size as f64) as usize, hi.clamp(0.0, size as f64) as usize)
+        };
+        let (x0, x1) = cell_range(x);
+        let (z0, z1) = cell_range(z);
+        if x0 >= x1 || z0 >= z1 {
+            return;
+        }
+        let width = x1 - x0;
+        let height = z1 - z0;
+        let read = |cx: usize, cz: usize| height_map.read(fine, cx, cz);
+        let perlin = match self.op {
+            BrushOp::Noise { seed, .. } => Some(Perlin::new(seed)),
+            _ => None,
+        };
+        let mut heights = Vec::with_capacity(width * height);
+        for cz in z0..z1 {
+            for cx in x0..x1 {
+                let world_x = (cx as f64 + 0.5) * BLOCK_SIZE - half_size2;
+                let world_z = (cz as f64 + 0.5) * BLOCK_SIZE - half_size2;
+                let w = self.weight(world_x - x, world_z - z);
+                let h = read(cx, cz);
+                let new_h = match self.op {
+                    BrushOp::Raise => h + w * self.strength / HEIGHT_SCALE,
+                    BrushOp::Lower => h - w * self.strength / HEIGHT_SCALE,
+                    BrushOp::Smooth => {
+                        let mut sum = 0.0;
+                        let mut count = 0.0;
+                        for nz in cz.saturating_sub(1)..(cz + 2).min(size) {
+                            for nx in cx.saturating_sub(1)..(cx + 2).min(size) {
+                                sum += read(nx, nz);
+                                count += 1.0;
+                            }
+                        }
+                        h + (sum / count - h) * w * self.strength.clamp(0.0, 1.0)
+                    }
+                    BrushOp::Flatten { height } => h + (height / HEIGHT_SCALE - h) * w * self.strength.clamp(0.0, 1.0),
+                    BrushOp::Noise { scale, .. } => {
+                        let n = perlin.as_ref().unwrap().get([world_x / scale, world_z / scale]);
+                        h + n * w * self.strength / HEIGHT_SCALE
+                    }
+                };
+                heights.push(new_h);
+            }
+        }
+        height_map.write_region(x0, z0, width, height, &heights);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_brushes_leave_the_map_alone() {
+        let mut height_map = HeightMap::from_grid(8, &[0.0; 64], None);
+        let brush = Brush {
+            shape: BrushShape::Circle,
+            radius: 100.0,
+            falloff: Falloff::Linear,
+            strength: 50.0,
+            op: BrushOp::Noise { seed: 0, scale: 0.0 },
+        };
+        for brush in [brush, Brush { radius: f64::INFINITY, op: BrushOp::Raise, ..brush }, Brush { strength: f64::NAN, op: BrushOp::Raise, ..brush }] {
+            assert!(!brush.is_valid());
+            brush.apply(&mut height_map, 0.0, 0.0);
+        }
+        assert_eq!(height_map.revision(), 0);
+        let brush = Brush { op: BrushOp::Noise { seed: 0, scale: 200.0 }, ..brush };
+        brush.apply(&mut height_map, 0.0, 0.0);
+        assert_eq!(height_map.revision(), 1);
+    }
+
+    /// Cell (8, 8) of the 16x16 maps below; its centre is at world (20, 20).
+    const CENTRE: (f64, f64) = (20.0, 20.0);
+
+    fn brush(op: BrushOp, strength: f64) -> Brush {
+        Brush { shape: BrushShape::Circle, radius: 100.0, falloff: Falloff::Linear, strength, op }
+    }
+
+    fn flat_map() -> HeightMap {
+        HeightMap::from_grid(16, &[0.0; 256], None)
+    }
+
+    fn cell(height_map: &HeightMap, x: usize, z: usize) -> f64 {
+        height_map.read(height_map.num_levels()-1, x, z)
+    }
+
+    #[test]
+    fn raise_and_lower_move_cells_inside_the_radius() {
+        for (op, sign) in [(BrushOp::Raise, 1.0), (BrushOp::Lower, -1.0)] {
+            let mut height_map = flat_map();
+            brush(op, 50.0).apply(&mut height_map, CENTRE.0, CENTRE.1);
+            assert_eq!(cell(&height_map, 8, 8), sign * 50.0 / HEIGHT_SCALE);
+            // One cell (40 units) out, the linear falloff leaves 60%.
+            assert!((cell(&height_map, 9, 8) - sign * 0.6 * 50.0 / HEIGHT_SCALE).abs() < 1.0e-12);
+            // Three cells out is past the radius.
+            assert_eq!(cell(&height_map, 11, 8), 0.0);
+            assert_eq!(cell(&height_map, 0, 0), 0.0);
+        }
+    }
+
+    #[test]
+    fn smooth_spreads_a_spike() {
+        let mut heights = [0.0; 256];
+        heights[8 * 16 + 8] = 0.9;
+        let mut height_map = HeightMap::from_grid(16, &heights, None);
+        brush(BrushOp::Smooth, 1.0).apply(&mut height_map, CENTRE.0, CENTRE.1);
+        // The centre moves all the way to its 3x3 mean.
+        assert!((cell(&height_map, 8, 8) - 0.1).abs() < 1.0e-12);
+        assert!(cell(&height_map, 9, 8) > 0.0);
+        assert_eq!(cell(&height_map, 11, 8), 0.0);
+    }
+
+    #[test]
+    fn flatten_converges_to_the_target_height() {
+        let mut height_map = flat_map();
+        let brush = brush(BrushOp::Flatten { height: 200.0 }, 0.5);
+        for _ in 0..200 {
+            brush.apply(&mut height_map, CENTRE.0, CENTRE.1);
+        }
+        for (x, z) in [(8, 8), (9, 8), (8, 6), (7, 9)] {
+            assert!((cell(&height_map, x, z) - 0.2).abs() < 1.0e-6, "cell ({}, {})", x, z);
+        }
+        assert_eq!(cell(&height_map, 11, 8), 0.0);
+    }
+
+    #[test]
+    fn noise_is_fixed_in_the_world() {
+        let brush = brush(BrushOp::Noise { seed: 3, scale: 70.0 }, 100.0);
+        let mut a = flat_map();
+        let mut b = flat_map();
+        brush.apply(&mut a, CENTRE.0, CENTRE.1);
+        brush.apply(&mut b, CENTRE.0, CENTRE.1);
+        let changed = (0..256).filter(|&i| cell(&a, i % 16, i / 16) != 0.0).count();
+        assert!(changed > 1);
+        assert!((0..256).all(|i| cell(&a, i % 16, i / 16) == cell(&b, i % 16, i / 16)));
+        assert_eq!(cell(&a, 11, 8), 0.0);
+    }
+}
@@ -1,13 +1,21 @@
 use std::ops::{Add, Sub, Mul, Div, Neg};
-use crate::{Ray2, Sqrt, Transform3, Vec2, Vec3, Zero, One};
+use crate::{LevelOfDetail, Quaternion, Ray2, Sqrt, Transform3, Vec2, Vec3, Zero, One};
 
 pub struct Camera<T> {
     pub space: Transform3<T>,
     pub screen_width: T,
     pub screen_height: T,
     pub screen_dist: T,
+    /// Vertical offset of the horizon in pixels, used to fake pitch by y-shearing.
+    pub shear_y: T,
+    /// Widest, in pixels, a pyramid node may appear before far terrain is walked at a coarser
+    /// level; zero or less walks everything at full resolution.
+    pub pixel_error: T,
 }
 
+/// Pitch is clamped to this many degrees either side of level, past which y-shearing breaks down.
+const MAX_PITCH: f64 = 75.0;
+
 impl<T: Copy> Camera<T> {
     pub fn screen_x_to_ray_xz(&self, x: T) -> Option<Ray2<T>>
     where
@@ -31,7 +39,7 @@ impl<T: Copy> Camera<T> {
         let pt2 = self.space.point_to_space(pt);
         let y = pt2.y * self.screen_dist / -pt2.z;
         let half = T::one() / (T::one() + T::one());
-        return half * self.screen_height - y;
+        half * self.screen_height - y + self.shear_y
     }
 
     pub fn project(&self, pt: Vec3<T>) -> Vec2<T>
@@ -44,9 +52,64 @@ impl<T: Copy> Camera<T> {
             pt2.y * self.screen_dist / -pt2.z,
         );
         let half = T::one() / (T::one() + T::one());
-        return Vec2::new(
+        Vec2::new(
             half * self.screen_width + pt3.x,
-            half * self.screen_height - pt3.y,
-        );
+            half * self.screen_height - pt3.y + self.shear_y,
+        )
+    }
+}
+
+impl Camera<f64> {
+    pub fn new(space: Transform3<f64>, screen_width: f64, screen_height: f64, fov_y: f64) -> Camera<f64> {
+        let screen_dist = 0.5 * screen_height / (0.5 * fov_y).to_radians().tan();
+        Camera {
+            space,
+            screen_width,
+            screen_height,
+            screen_dist,
+            shear_y: 0.0,
+            pixel_error: 0.0,
+        }
+    }
+
+    /// Decomposes the camera orientation into yaw, pitch and roll in radians, matching
+    /// `Quaternion::from_yaw_pitch_roll`.
+    pub fn yaw_pitch_roll(&self) -> (f64, f64, f64) {
+        let forward = self.space.vector_from_space(Vec3::new(0.0, 0.0, -1.0));
+        let right = self.space.vector_from_space(Vec3::new(1.0, 0.0, 0.0));
+        let yaw = (-forward.x).atan2(-forward.z);
+        let pitch = forward.y.clamp(-1.0, 1.0).asin();
+        let level = Quaternion::from_yaw_pitch_roll(yaw, pitch, 0.0);
+        let level_right = level.rotate(Vec3::new(1.0, 0.0, 0.0));
+        let level_up = level.rotate(Vec3::new(0.0, 1.0, 0.0));
+        let roll = right.dot(level_up).atan2(right.dot(level_right));
+        (yaw, pitch, roll)
+    }
+
+    /// Returns an upright camera at the same position and heading with pitch turned into a
+    /// horizon shear, for a render target of the given size. Roll is left to the caller.
+    pub fn upright(&self, screen_width: f64, screen_height: f64) -> Camera<f64> {
+        let (yaw, pitch, _) = self.yaw_pitch_roll();
+        let max_pitch = MAX_PITCH.to_radians();
+        let pitch = pitch.clamp(-max_pitch, max_pitch);
+        Camera {
+            space: Transform3::new(self.space.origin, Quaternion::from_yaw_pitch_roll(yaw, 0.0, 0.0)),
+            screen_width,
+            screen_height,
+            screen_dist: self.screen_dist,
+            shear_y: self.screen_dist * pitch.tan(),
+            pixel_error: self.pixel_error,
+        }
+    }
+
+    /// The level of detail for ray walks from this camera, if `pixel_error` enables one.
+    pub fn level_of_detail(&self) -> Option<LevelOfDetail> {
+        if self.pixel_error <= 0.0 {
+            return None;
+        }
+        Some(LevelOfDetail {
+            pixel_error: self.pixel_error,
+            screen_dist: self.screen_dist,
+        })
     }
 }
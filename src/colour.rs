@@ -0,0 +1,28 @@
+/// Packs RGB into the framebuffer's `0xAABBGGRR` layout with full alpha.
+pub fn pack_rgb(rgb: [u8; 3]) -> u32 {
+    0xFF000000 | ((rgb[2] as u32) << 16) | ((rgb[1] as u32) << 8) | (rgb[0] as u32)
+}
+
+/// Blends `a` towards `b` by `t` in `0.0..=1.0`.
+pub fn lerp_rgb(a: [u8; 3], b: [u8; 3], t: f64) -> [u8; 3] {
+    let t = t.clamp(0.0, 1.0);
+    let mut r = [0u8; 3];
+    for i in 0..3 {
+        r[i] = (a[i] as f64 + (b[i] as f64 - a[i] as f64) * t).round() as u8;
+    }
+    r
+}
+
+/// Multiplies each channel by `factor`, clamping to the valid range.
+pub fn scale_rgb(rgb: [u8; 3], factor: f64) -> [u8; 3] {
+    let mut r = [0u8; 3];
+    for i in 0..3 {
+        r[i] = (rgb[i] as f64 * factor).round().clamp(0.0, 255.0) as u8;
+    }
+    r
+}
+
+/// Unpacks the RGB channels of a `0xAABBGGRR` pixel.
+pub fn unpack_rgb(pixel: u32) -> [u8; 3] {
+    [pixel as u8, (pixel >> 8) as u8, (pixel >> 16) as u8]
+}
@@ -3,6 +3,124 @@ extern crate noise;
 
 use noise::{core::worley::ReturnType, utils::*, *};
 
+/// Parameters for `make_planet`. `Default` reproduces the original hard-coded planet.
+pub struct PlanetConfig {
+    /// Planet seed. Change this to generate a different planet.
+    pub seed: u32,
+
+    /// Frequency of the planet's continents. Higher frequency produces
+    /// smaller, more numerous continents. This value is measured in radians.
+    pub continent_frequency: f64,
+
+    /// Lacunarity of the planet's continents. Changing this value produces
+    /// slightly different continents. For the best results, this value should
+    /// be random, but close to 2.0.
+    pub continent_lacunarity: f64,
+
+    /// Lacunarity of the planet's mountains. Changing the value produces
+    /// slightly different mountains. For the best results, this value should
+    /// be random, but close to 2.0.
+    pub mountain_lacunarity: f64,
+
+    /// Lacunarity of the planet's hills. Changing this value produces
+    /// slightly different hills. For the best results, this value should be
+    /// random, but close to 2.0.
+    pub hills_lacunarity: f64,
+
+    /// Lacunarity of the planet's plains. Changing this value produces
+    /// slightly different plains. For the best results, this value should be
+    /// random, but close to 2.0.
+    pub plains_lacunarity: f64,
+
+    /// Lacunarity of the planet's badlands. Changing this value produces
+    /// slightly different badlands. For the best results, this value should
+    /// be random, but close to 2.0.
+    pub badlands_lacunarity: f64,
+
+    /// Specifies the "twistiness" of the mountains.
+    pub mountains_twist: f64,
+
+    /// Specifies the "twistiness" of the hills.
+    pub hills_twist: f64,
+
+    /// Specifies the "twistiness" of the badlands.
+    pub badlands_twist: f64,
+
+    /// Specifies the planet's sea level. This value must be between -1.0
+    /// (minimum planet elevation) and +1.0 (maximum planet elevation).
+    pub sea_level: f64,
+
+    /// Specifies the level on the planet in which continental shelves appear.
+    /// This value must be between -1.0 (minimum planet elevation) and +1.0
+    /// (maximum planet elevation), and must be less than `sea_level`.
+    pub shelf_level: f64,
+
+    /// Determines the amount of mountainous terrain that appears on the
+    /// planet. Values range from 0.0 (no mountains) to 1.0 (all terrain is
+    /// covered in mountains). Mountains terrain will overlap hilly terrain.
+    /// Because the badlands terrain may overlap parts of the mountainous
+    /// terrain, setting `mountains_amount` to 1.0 may not completely cover the
+    /// terrain in mountains.
+    pub mountains_amount: f64,
+
+    /// Determines the amount of badlands terrain that covers the planet.
+    /// Values range from 0.0 (no badlands) to 1.0 (all terrain is covered in
+    /// badlands). Badlands terrain will overlap any other type of terrain.
+    pub badlands_amount: f64,
+
+    /// Offset to apply to the terrain type definition. Low values (< 1.0)
+    /// cause the rough areas to appear only at high elevations. High values
+    /// (> 2.0) cause the rough areas to appear at any elevation. The
+    /// percentage of rough areas on the planet are independent of this value.
+    pub terrain_offset: f64,
+
+    /// Specifies the amount of "glaciation" on the mountains. This value
+    /// should be close to 1.0 and greater than 1.0.
+    pub mountain_glaciation: f64,
+
+    /// Maximum depth of the rivers, in planetary elevation units.
+    pub river_depth: f64,
+
+    /// Width and height of the generated noise map in samples.
+    pub size: usize,
+
+    /// Range of planet coordinates sampled across the width of the map.
+    pub x_bounds: (f64, f64),
+
+    /// Range of planet coordinates sampled across the height of the map.
+    pub y_bounds: (f64, f64),
+}
+
+impl Default for PlanetConfig {
+    fn default() -> Self {
+        let offset_x = 9.6;
+        let offset_y = 0.0;
+        let zoom = 3.0;
+        PlanetConfig {
+            seed: 0,
+            continent_frequency: 1.0,
+            continent_lacunarity: 2.208984375,
+            mountain_lacunarity: 2.142578125,
+            hills_lacunarity: 2.162109375,
+            plains_lacunarity: 2.314453125,
+            badlands_lacunarity: 2.212890625,
+            mountains_twist: 1.0,
+            hills_twist: 1.0,
+            badlands_twist: 1.0,
+            sea_level: 0.0,
+            shelf_level: -0.375,
+            mountains_amount: 0.5,
+            badlands_amount: 0.3125,
+            terrain_offset: 1.0,
+            mountain_glaciation: 1.375,
+            river_depth: 0.0234375,
+            size: 256,
+            x_bounds: ((-2.0 + offset_x) / zoom, (2.0 + offset_y) / zoom),
+            y_bounds: ((-2.0 + offset_x) / zoom, (2.0 + offset_y) / zoom),
+        }
+    }
+}
+
 /// This example demonstrates how to use the noise-rs library to generate
 /// terrain elevations for a complex planetary surface.
 ///
@@ -60,94 +178,18 @@ use noise::{core::worley::ReturnType, utils::*, *};
 /// A description for each group and subgroup can be found above the source
 /// code for that group and subgroup.
 #[allow(non_snake_case)]
-pub fn make_planet() -> (NoiseMap, ColorGradient) {
-    /// Planet seed. Change this to generate a different planet.
-    const CURRENT_SEED: u32 = 0;
-
-    /// Frequency of the planet's continents. Higher frequency produces
-    /// smaller, more numerous continents. This value is measured in radians.
-    const CONTINENT_FREQUENCY: f64 = 1.0;
-
-    /// Lacunarity of the planet's continents. Changing this value produces
-    /// slightly different continents. For the best results, this value should
-    /// be random, but close to 2.0.
-    const CONTINENT_LACUNARITY: f64 = 2.208984375;
-
-    /// Lacunarity of the planet's mountains. Changing the value produces
-    /// slightly different mountains. For the best results, this value should
-    /// be random, but close to 2.0.
-    const MOUNTAIN_LACUNARITY: f64 = 2.142578125;
-
-    /// Lacunarity of the planet's hills. Changing this value produces
-    /// slightly different hills. For the best results, this value should be
-    /// random, but close to 2.0.
-    const HILLS_LACUNARITY: f64 = 2.162109375;
-
-    /// Lacunarity of the planet's plains. Changing this value produces
-    /// slightly different plains. For the best results, this value should be
-    /// random, but close to 2.0.
-    const PLAINS_LACUNARITY: f64 = 2.314453125;
-
-    /// Lacunarity of the planet's badlands. Changing this value produces
-    /// slightly different badlands. For the best results, this value should
-    /// be random, but close to 2.0.
-    const BADLANDS_LACUNARITY: f64 = 2.212890625;
-
-    /// Specifies the "twistiness" of the mountains.
-    const MOUNTAINS_TWIST: f64 = 1.0;
-
-    /// Specifies the "twistiness" of the hills.
-    const HILLS_TWIST: f64 = 1.0;
-
-    /// Specifies the "twistiness" of the badlands.
-    const BADLANDS_TWIST: f64 = 1.0;
-
-    /// Specifies the planet's sea level. This value must be between -1.0
-    /// (minimum planet elevation) and +1.0 (maximum planet elevation).
-    const SEA_LEVEL: f64 = 0.0;
-
-    /// Specifies the level on the planet in which continental shelves appear.
-    /// This value must be between -1.0 (minimum planet elevation) and +1.0
-    /// (maximum planet elevation), and must be less than `SEA_LEVEL`.
-    const SHELF_LEVEL: f64 = -0.375;
-
-    /// Determines the amount of mountainous terrain that appears on the
-    /// planet. Values range from 0.0 (no mountains) to 1.0 (all terrain is
-    /// covered in mountains). Mountains terrain will overlap hilly terrain.
-    /// Because the badlands terrain may overlap parts of the mountainous
-    /// terrain, setting `MOUNTAINS_AMOUNT` to 1.0 may not completely cover the
-    /// terrain in mountains.
-    const MOUNTAINS_AMOUNT: f64 = 0.5;
-
-    /// Determines the amount of hilly terrain that appears on the planet.
-    /// Values range from 0.0 (no hills) to 1.0 (all terrain is covered in
-    /// hills). This value must be less than `MOUNTAINS_AMOUNT`. Because the
-    /// mountains terrain will overlap parts of the hilly terrain, and the
-    /// badlands terrain may overlap parts of the hilly terrain, setting
-    /// `HILLS_AMOUNT` to 1.0 may not completely cover the terrain in hills.
-    const HILLS_AMOUNT: f64 = (1.0 + MOUNTAINS_AMOUNT) / 2.0;
-
-    /// Determines the amount of badlands terrain that covers the planet.
-    /// Values range from 0.0 (no badlands) to 1.0 (all terrain is covered in
-    /// badlands). Badlands terrain will overlap any other type of terrain.
-    const BADLANDS_AMOUNT: f64 = 0.3125;
-
-    /// Offset to apply to the terrain type definition. Low values (< 1.0)
-    /// cause the rough areas to appear only at high elevations. High values
-    /// (> 2.0) cause the rough areas to appear at any elevation. The
-    /// percentage of rough areas on the planet are independent of this value.
-    const TERRAIN_OFFSET: f64 = 1.0;
-
-    /// Specifies the amount of "glaciation" on the mountains. This value
-    /// should be close to 1.0 and greater than 1.0.
-    const MOUNTAIN_GLACIATION: f64 = 1.375;
-
-    /// Scaling to apply to the base continent elevations, in planetary
-    /// elevation units.
-    const CONTINENT_HEIGHT_SCALE: f64 = (1.0 - SEA_LEVEL) / 4.0;
-
-    /// Maximum depth of the rivers, in planetary elevation units.
-    const RIVER_DEPTH: f64 = 0.0234375;
+pub fn make_planet(config: &PlanetConfig) -> (NoiseMap, ColorGradient) {
+    // Determines the amount of hilly terrain that appears on the planet.
+    // Values range from 0.0 (no hills) to 1.0 (all terrain is covered in
+    // hills). This value must be less than `mountains_amount`. Because the
+    // mountains terrain will overlap parts of the hilly terrain, and the
+    // badlands terrain may overlap parts of the hilly terrain, setting
+    // `hills_amount` to 1.0 may not completely cover the terrain in hills.
+    let hills_amount = (1.0 + config.mountains_amount) / 2.0;
+
+    // Scaling to apply to the base continent elevations, in planetary
+    // elevation units.
+    let continent_height_scale = (1.0 - config.sea_level) / 4.0;
 
     // ////////////////////////////////////////////////////////////////////////
     // Function group: continent definition
@@ -165,14 +207,14 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // -1.0 represents the lowest elevations and +1.0 represents the highest
     // elevations.
     //
-    fn baseContinentDef() -> impl NoiseFn<f64, 3> {
+    fn baseContinentDef(config: &PlanetConfig) -> impl NoiseFn<f64, 3> {
         // 1: [Continent module]: This FBM module generates the continents. This
         // noise function has a high number of octaves so that detail is visible at
         // high zoom levels.
-        let baseContinentDef_fb0 = Fbm::<Perlin>::new(CURRENT_SEED)
-            .set_frequency(CONTINENT_FREQUENCY)
+        let baseContinentDef_fb0 = Fbm::<Perlin>::new(config.seed)
+            .set_frequency(config.continent_frequency)
             .set_persistence(0.5)
-            .set_lacunarity(CONTINENT_LACUNARITY)
+            .set_lacunarity(config.continent_lacunarity)
             .set_octaves(14);
 
         //    debug::render_noise_module("complexplanet_images/00_0_baseContinentDef_fb0\
@@ -186,16 +228,16 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
         // output value from the continent module so that very high values appear
         // near sea level. This defines the positions of the mountain ranges.
         let baseContinentDef_cu = Curve::new(baseContinentDef_fb0)
-            .add_control_point(-2.0000 + SEA_LEVEL, -1.625 + SEA_LEVEL)
-            .add_control_point(-1.0000 + SEA_LEVEL, -1.375 + SEA_LEVEL)
-            .add_control_point(0.0000 + SEA_LEVEL, -0.375 + SEA_LEVEL)
-            .add_control_point(0.0625 + SEA_LEVEL, 0.125 + SEA_LEVEL)
-            .add_control_point(0.1250 + SEA_LEVEL, 0.250 + SEA_LEVEL)
-            .add_control_point(0.2500 + SEA_LEVEL, 1.000 + SEA_LEVEL)
-            .add_control_point(0.5000 + SEA_LEVEL, 0.250 + SEA_LEVEL)
-            .add_control_point(0.7500 + SEA_LEVEL, 0.250 + SEA_LEVEL)
-            .add_control_point(1.0000 + SEA_LEVEL, 0.500 + SEA_LEVEL)
-            .add_control_point(2.0000 + SEA_LEVEL, 0.500 + SEA_LEVEL);
+            .add_control_point(-2.0000 + config.sea_level, -1.625 + config.sea_level)
+            .add_control_point(-1.0000 + config.sea_level, -1.375 + config.sea_level)
+            .add_control_point(0.0000 + config.sea_level, -0.375 + config.sea_level)
+            .add_control_point(0.0625 + config.sea_level, 0.125 + config.sea_level)
+            .add_control_point(0.1250 + config.sea_level, 0.250 + config.sea_level)
+            .add_control_point(0.2500 + config.sea_level, 1.000 + config.sea_level)
+            .add_control_point(0.5000 + config.sea_level, 0.250 + config.sea_level)
+            .add_control_point(0.7500 + config.sea_level, 0.250 + config.sea_level)
+            .add_control_point(1.0000 + config.sea_level, 0.500 + config.sea_level)
+            .add_control_point(2.0000 + config.sea_level, 0.500 + config.sea_level);
 
         //    debug::render_noise_module("complexplanet_images/00_1_baseContinentDef_cu\
         //    .png",
@@ -208,10 +250,10 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
         // used by subsequent noise functions to carve out chunks from the
         // mountain ranges within the continent-with-ranges module so that the
         // mountain ranges will not be completely impassible.
-        let baseContinentDef_fb1 = Fbm::<Perlin>::new(CURRENT_SEED + 1)
-            .set_frequency(CONTINENT_FREQUENCY * 4.34375)
+        let baseContinentDef_fb1 = Fbm::<Perlin>::new(config.seed.wrapping_add(1))
+            .set_frequency(config.continent_frequency * 4.34375)
             .set_persistence(0.5)
-            .set_lacunarity(CONTINENT_LACUNARITY)
+            .set_lacunarity(config.continent_lacunarity)
             .set_octaves(11);
 
         //    debug::render_noise_module("complexplanet_images/00_2_baseContinentDef_fb1\
@@ -261,9 +303,7 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
 
         // 7: [Base-continent-definition subgroup]: Caches the output value from
         // the clamped-continent module.
-        let baseContinentDef = Cache::new(baseContinentDef_cl);
-
-        baseContinentDef
+        Cache::new(baseContinentDef_cl)
     }
 
     //    debug::render_noise_module("complexplanet_images/00_5_baseContinentDef.png",
@@ -288,10 +328,10 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // 1: [Coarse-turbulence module]: This turbulence module warps the output
     // value from the base-continent-definition subgroup, adding some coarse
     // detail to it.
-    let continentDef_tu0 = Turbulence::<_, Perlin>::new(baseContinentDef())
-        .set_seed(CURRENT_SEED + 10)
-        .set_frequency(CONTINENT_FREQUENCY * 15.25)
-        .set_power(CONTINENT_FREQUENCY / 113.75)
+    let continentDef_tu0 = Turbulence::<_, Perlin>::new(baseContinentDef(config))
+        .set_seed(config.seed.wrapping_add(10))
+        .set_frequency(config.continent_frequency * 15.25)
+        .set_power(config.continent_frequency / 113.75)
         .set_roughness(13);
 
     //    debug::render_noise_module("complexplanet_images/01_0_continentDef_tu0.png",
@@ -305,9 +345,9 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // higher frequency, but lower power, than the coarse-turbulence module,
     // adding some intermediate detail to it.
     let continentDef_tu1 = Turbulence::<_, Perlin>::new(continentDef_tu0)
-        .set_seed(CURRENT_SEED + 11)
-        .set_frequency(CONTINENT_FREQUENCY * 47.25)
-        .set_power(CONTINENT_FREQUENCY / 433.75)
+        .set_seed(config.seed.wrapping_add(11))
+        .set_frequency(config.continent_frequency * 47.25)
+        .set_power(config.continent_frequency / 433.75)
         .set_roughness(12);
 
     //    debug::render_noise_module("complexplanet_images/01_1_continentDef_tu1.png",
@@ -321,9 +361,9 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // turbulence has a higher frequency, but lower power, than the
     // intermediate-turbulence module, adding some fine detail to it.
     let continentDef_tu2 = Turbulence::<_, Perlin>::new(continentDef_tu1)
-        .set_seed(CURRENT_SEED + 12)
-        .set_frequency(CONTINENT_FREQUENCY * 95.25)
-        .set_power(CONTINENT_FREQUENCY / 1019.75)
+        .set_seed(config.seed.wrapping_add(12))
+        .set_frequency(config.continent_frequency * 95.25)
+        .set_power(config.continent_frequency / 1019.75)
         .set_roughness(11);
 
     //    debug::render_noise_module("complexplanet_images/01_2_continentDef_tu2.png",
@@ -342,8 +382,8 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // transition.  In effect, only the higher areas of the base-continent-
     // definition subgroup become warped; the underwater and coastal areas
     // remain unaffected.
-    let continentDef_se = Select::new(baseContinentDef(), continentDef_tu2, baseContinentDef())
-        .set_bounds(SEA_LEVEL - 0.0375, SEA_LEVEL + 1000.0375)
+    let continentDef_se = Select::new(baseContinentDef(config), continentDef_tu2, baseContinentDef(config))
+        .set_bounds(config.sea_level - 0.0375, config.sea_level + 1000.0375)
         .set_falloff(0.0625);
 
     //    debug::render_noise_module("complexplanet_images/01_3_continentDef_se.png",
@@ -389,9 +429,9 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // areas may now appear in the the ocean, creating rocky islands and
     // fjords.
     let terrainTypeDef_tu = Turbulence::<_, Perlin>::new(&continentDef)
-        .set_seed(CURRENT_SEED + 20)
-        .set_frequency(CONTINENT_FREQUENCY * 18.125)
-        .set_power(CONTINENT_FREQUENCY / 20.59375 * TERRAIN_OFFSET)
+        .set_seed(config.seed.wrapping_add(20))
+        .set_frequency(config.continent_frequency * 18.125)
+        .set_power(config.continent_frequency / 20.59375 * config.terrain_offset)
         .set_roughness(3);
 
     // 2: [Roughness-probability-shift module]: This terracing module sharpens
@@ -401,7 +441,7 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // terrain.
     let terrainTypeDef_te = Terrace::new(terrainTypeDef_tu)
         .add_control_point(-1.00)
-        .add_control_point(SHELF_LEVEL + SEA_LEVEL / 2.0)
+        .add_control_point(config.shelf_level + config.sea_level / 2.0)
         .add_control_point(1.00);
 
     // 3: [Terrain-type-definition group]: Caches the output value from the
@@ -425,9 +465,9 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
 
     // 1: [Mountain-ridge module]: This ridged-multifractal-noise function
     // generates the mountain ridges.
-    let mountainBaseDef_rm0 = RidgedMulti::<Perlin>::new(CURRENT_SEED + 30)
+    let mountainBaseDef_rm0 = RidgedMulti::<Perlin>::new(config.seed.wrapping_add(30))
         .set_frequency(1723.0)
-        .set_lacunarity(MOUNTAIN_LACUNARITY)
+        .set_lacunarity(config.mountain_lacunarity)
         .set_octaves(4);
 
     // 2: [Scaled-mountain-ridge module]: Next, a scale/bias module scales the
@@ -444,9 +484,9 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // of the valleys. Note that this noise function generates ridged-multifractal
     // noise using only one octave; this information will be important in the
     // next step.
-    let mountainBaseDef_rm1 = RidgedMulti::<Perlin>::new(CURRENT_SEED + 31)
+    let mountainBaseDef_rm1 = RidgedMulti::<Perlin>::new(config.seed.wrapping_add(31))
         .set_frequency(367.0)
-        .set_lacunarity(MOUNTAIN_LACUNARITY)
+        .set_lacunarity(config.mountain_lacunarity)
         .set_octaves(1);
 
     // 4: [Scaled-river-valley module]: Next, a scale/bias module applies a
@@ -480,9 +520,9 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // value from the mountain-and-valleys module, adding some coarse detail to
     // it.
     let mountainBaseDef_tu0 = Turbulence::<_, Perlin>::new(mountainBaseDef_bl)
-        .set_seed(CURRENT_SEED + 32)
+        .set_seed(config.seed.wrapping_add(32))
         .set_frequency(1337.0)
-        .set_power(1.0 / 6730.0 * MOUNTAINS_TWIST)
+        .set_power(1.0 / 6730.0 * config.mountains_twist)
         .set_roughness(4);
 
     // 8: [Warped-mountains-and-valleys module]: This turbulence module warps
@@ -490,9 +530,9 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // a higher frequency, but lower power, than the coarse-turbulence module,
     // adding some fine detail to it.
     let mountainBaseDef_tu1 = Turbulence::<_, Perlin>::new(mountainBaseDef_tu0)
-        .set_seed(CURRENT_SEED + 33)
+        .set_seed(config.seed.wrapping_add(33))
         .set_frequency(21221.0)
-        .set_power(1.0 / 120157.0 * MOUNTAINS_TWIST)
+        .set_power(1.0 / 120157.0 * config.mountains_twist)
         .set_roughness(6);
 
     // 9: [Mountain-base-definition subgroup]: Caches the output value from the
@@ -512,17 +552,17 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // 1: [Mountain-basis-0 module]: This ridged-multifractal-noise function,
     // along with the mountain-basis-1 module, generates the individual
     // mountains.
-    let mountainousHigh_rm0 = RidgedMulti::<Perlin>::new(CURRENT_SEED + 40)
+    let mountainousHigh_rm0 = RidgedMulti::<Perlin>::new(config.seed.wrapping_add(40))
         .set_frequency(2371.0)
-        .set_lacunarity(MOUNTAIN_LACUNARITY)
+        .set_lacunarity(config.mountain_lacunarity)
         .set_octaves(3);
 
     // 2: [Mountain-basis-1 module]: This ridged-multifractal-noise function,
     // along with the mountain-basis-0 module, generates the individual
     // mountains.
-    let mountainousHigh_rm1 = RidgedMulti::<Perlin>::new(CURRENT_SEED + 41)
+    let mountainousHigh_rm1 = RidgedMulti::<Perlin>::new(config.seed.wrapping_add(41))
         .set_frequency(2341.0)
-        .set_lacunarity(MOUNTAIN_LACUNARITY)
+        .set_lacunarity(config.mountain_lacunarity)
         .set_octaves(3);
 
     // 3: [High-mountains module]: Next, a maximum-value module causes more
@@ -535,9 +575,9 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // 4: [Warped-high-mountains module]: This turbulence module warps the
     // output value from the high-mountains module, adding some detail to it.
     let mountainousHigh_tu = Turbulence::<_, Perlin>::new(mountainousHigh_ma)
-        .set_seed(CURRENT_SEED + 42)
+        .set_seed(config.seed.wrapping_add(42))
         .set_frequency(31511.0)
-        .set_power(1.0 / 180371.0 * MOUNTAINS_TWIST)
+        .set_power(1.0 / 180371.0 * config.mountains_twist)
         .set_roughness(4);
 
     // 5: [High-mountainous-terrain subgroup]: Caches the output value from the
@@ -557,17 +597,17 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // 1: [Lowland-basis-0 module]: This ridged-multifractal-noise function,
     // along with the lowland-basis-1 module, produces the low mountainous
     // terrain.
-    let mountainousLow_rm0 = RidgedMulti::<Perlin>::new(CURRENT_SEED + 50)
+    let mountainousLow_rm0 = RidgedMulti::<Perlin>::new(config.seed.wrapping_add(50))
         .set_frequency(1381.0)
-        .set_lacunarity(MOUNTAIN_LACUNARITY)
+        .set_lacunarity(config.mountain_lacunarity)
         .set_octaves(8);
 
     // 1: [Lowland-basis-1 module]: This ridged-multifractal-noise function,
     // along with the lowland-basis-0 module, produces the low mountainous
     // terrain.
-    let mountainousLow_rm1 = RidgedMulti::<Perlin>::new(CURRENT_SEED + 51)
+    let mountainousLow_rm1 = RidgedMulti::<Perlin>::new(config.seed.wrapping_add(51))
         .set_frequency(1427.0)
-        .set_lacunarity(MOUNTAIN_LACUNARITY)
+        .set_lacunarity(config.mountain_lacunarity)
         .set_octaves(8);
 
     // 3: [Low-mountainous-terrain module]: This multiplication module combines
@@ -651,7 +691,7 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // those mountains. This exponential-curve module expects the output value
     // to range from -1.0 to +1.0.
     let mountainousTerrain_ex =
-        Exponent::new(mountainousTerrain_sb2).set_exponent(MOUNTAIN_GLACIATION);
+        Exponent::new(mountainousTerrain_sb2).set_exponent(config.mountain_glaciation);
 
     let mountainousTerrain = Cache::new(mountainousTerrain_ex);
 
@@ -669,10 +709,10 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     //
 
     // 1: [Hills module]: This billow-noise function generates the hills.
-    let hillyTerrain_bi = Billow::<Perlin>::new(CURRENT_SEED + 60)
+    let hillyTerrain_bi = Billow::<Perlin>::new(config.seed.wrapping_add(60))
         .set_frequency(1663.0)
         .set_persistence(0.5)
-        .set_lacunarity(HILLS_LACUNARITY)
+        .set_lacunarity(config.hills_lacunarity)
         .set_octaves(6);
 
     // 2: [Scaled-hills module]: Next, a scale/bias module scales the output
@@ -686,9 +726,9 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // appear in between the valleys. Note that this noise function generates
     // ridged-multifractal noise using only one octave; this information will be
     // important in the next step.
-    let hillyTerrain_rm = RidgedMulti::<Perlin>::new(CURRENT_SEED + 61)
+    let hillyTerrain_rm = RidgedMulti::<Perlin>::new(config.seed.wrapping_add(61))
         .set_frequency(367.5)
-        .set_lacunarity(HILLS_LACUNARITY)
+        .set_lacunarity(config.hills_lacunarity)
         .set_octaves(1);
 
     // 4: [Scaled-river-valley module]: Next, a scale/bias module applies a
@@ -732,9 +772,9 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // value from the increased-slope-hilly-terrain module, adding some
     // coarse detail to it.
     let hillyTerrain_tu0 = Turbulence::<_, Perlin>::new(hillyTerrain_ex)
-        .set_seed(CURRENT_SEED + 62)
+        .set_seed(config.seed.wrapping_add(62))
         .set_frequency(1531.0)
-        .set_power(1.0 / 16921.0 * HILLS_TWIST)
+        .set_power(1.0 / 16921.0 * config.hills_twist)
         .set_roughness(4);
 
     // 10: [Warped-hilly-terrain module]: This turbulence module warps the
@@ -742,9 +782,9 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // higher frequency, but lower power, than the coarse-turbulence module,
     // adding some fine detail to it.
     let hillyTerrain_tu1 = Turbulence::<_, Perlin>::new(hillyTerrain_tu0)
-        .set_seed(CURRENT_SEED + 63)
+        .set_seed(config.seed.wrapping_add(63))
         .set_frequency(21617.0)
-        .set_power(1.0 / 117529.0 * HILLS_TWIST)
+        .set_power(1.0 / 117529.0 * config.hills_twist)
         .set_roughness(6);
 
     // 11: [Hilly-terrain group]: Caches the output value from the warped-hilly-
@@ -771,10 +811,10 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
 
     // 1: [Plains-basis-0 module]: This billow-noise function, along with the
     // plains-basis-1 module, produces the plains.
-    let plainsTerrain_bi0 = Billow::<Perlin>::new(CURRENT_SEED + 70)
+    let plainsTerrain_bi0 = Billow::<Perlin>::new(config.seed.wrapping_add(70))
         .set_frequency(1097.5)
         .set_persistence(0.5)
-        .set_lacunarity(PLAINS_LACUNARITY)
+        .set_lacunarity(config.plains_lacunarity)
         .set_octaves(8);
 
     // 2: [Positive-plains-basis-0 module]: This scale/bias module makes the
@@ -787,10 +827,10 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
 
     // 3: [Plains-basis-1 module]: This billow-noise function, along with the
     // plains-basis-2 module, produces the plains.
-    let plainsTerrain_bi1 = Billow::<Perlin>::new(CURRENT_SEED + 71)
+    let plainsTerrain_bi1 = Billow::<Perlin>::new(config.seed.wrapping_add(71))
         .set_frequency(1097.5)
         .set_persistence(0.5)
-        .set_lacunarity(PLAINS_LACUNARITY)
+        .set_lacunarity(config.plains_lacunarity)
         .set_octaves(8);
 
     // 4: [Positive-plains-basis-1 module]: This scale/bias module makes the
@@ -833,9 +873,9 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // 1: [Sand-dunes module]: This ridged-multifractal-noise function generates
     // sand dunes. This ridged-multifractal noise is generated with a single
     // octave, which makes very smooth dunes.
-    let badlandsSand_rm = RidgedMulti::<Perlin>::new(CURRENT_SEED + 80)
+    let badlandsSand_rm = RidgedMulti::<Perlin>::new(config.seed.wrapping_add(80))
         .set_frequency(6163.5)
-        .set_lacunarity(BADLANDS_LACUNARITY)
+        .set_lacunarity(config.badlands_lacunarity)
         .set_octaves(1);
 
     // 2: [Scaled-sand-dunes module]: This scale/bias module shrinks the dune
@@ -849,7 +889,7 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // generate the detail to add to the dunes. By enabling the distance
     // algorithm, small polygonal pits are generated; the edges of the pits
     // are joined to the edges of nearby pits.
-    let badlandsSand_wo = Worley::new(CURRENT_SEED + 81)
+    let badlandsSand_wo = Worley::new(config.seed.wrapping_add(81))
         .set_frequency(16183.25)
         .set_return_type(ReturnType::Distance);
 
@@ -880,10 +920,10 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
 
     // 1: [Cliff-basis module]: This Perlin-noise function generates some coherent
     // noise that will be used to generate the cliffs.
-    let badlandsCliffs_fb = Fbm::<Perlin>::new(CURRENT_SEED + 90)
-        .set_frequency(CONTINENT_FREQUENCY * 839.0)
+    let badlandsCliffs_fb = Fbm::<Perlin>::new(config.seed.wrapping_add(90))
+        .set_frequency(config.continent_frequency * 839.0)
         .set_persistence(0.5)
-        .set_lacunarity(BADLANDS_LACUNARITY)
+        .set_lacunarity(config.badlands_lacunarity)
         .set_octaves(6);
 
     // 2: [Cliff-shaping module]: Next, this curve module applies a curve to
@@ -919,9 +959,9 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // 5: [Coarse-turbulence module]: This turbulence module warps the output
     // value from the terraced-cliffs module, adding some coarse detail to it.
     let badlandsCliffs_tu0 = Turbulence::<_, Perlin>::new(badlandsCliffs_te)
-        .set_seed(CURRENT_SEED + 91)
+        .set_seed(config.seed.wrapping_add(91))
         .set_frequency(16111.0)
-        .set_power(1.0 / 141539.0 * BADLANDS_TWIST)
+        .set_power(1.0 / 141539.0 * config.badlands_twist)
         .set_roughness(3);
 
     // 6: [Warped-cliffs module]: This turbulence module warps the output value
@@ -929,9 +969,9 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // frequency, but lower power, than the coarse-turbulence module, adding
     // some fine detail to it.
     let badlandsCliffs_tu1 = Turbulence::<_, Perlin>::new(badlandsCliffs_tu0)
-        .set_seed(CURRENT_SEED + 92)
+        .set_seed(config.seed.wrapping_add(92))
         .set_frequency(36107.0)
-        .set_power(1.0 / 211543.0 * BADLANDS_TWIST)
+        .set_power(1.0 / 211543.0 * config.badlands_twist)
         .set_roughness(3);
 
     // 7: [Badlands-cliffs subgroup]: Caches the output value from the warped-
@@ -990,9 +1030,9 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
 
     // 1: [Large-river-basis module]: This ridged-multifractal-noise function
     // creates the large, deep rivers.
-    let riverPositions_rm0 = RidgedMulti::<Perlin>::new(CURRENT_SEED + 100)
+    let riverPositions_rm0 = RidgedMulti::<Perlin>::new(config.seed.wrapping_add(100))
         .set_frequency(18.75)
-        .set_lacunarity(CONTINENT_LACUNARITY)
+        .set_lacunarity(config.continent_lacunarity)
         .set_octaves(1);
 
     // 2: [Large-river-curve module]: This curve module applies a curve to the
@@ -1010,9 +1050,9 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
 
     // 3: [Small-river-basis module]: This ridged-multifractal-noise function
     // creates the small, shallow rivers.
-    let riverPositions_rm1 = RidgedMulti::<Perlin>::new(CURRENT_SEED + 101)
+    let riverPositions_rm1 = RidgedMulti::<Perlin>::new(config.seed.wrapping_add(101))
         .set_frequency(43.25)
-        .set_lacunarity(CONTINENT_LACUNARITY)
+        .set_lacunarity(config.continent_lacunarity)
         .set_octaves(1);
 
     // 4: [Small-river-curve module]: This curve module applies a curve to the
@@ -1038,7 +1078,7 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     //    from the combined-rivers module, which twists the rivers.  The high
     //    roughness produces less-smooth rivers.
     let riverPositions_tu = Turbulence::<_, Perlin>::new(riverPositions_mi)
-        .set_seed(CURRENT_SEED + 102)
+        .set_seed(config.seed.wrapping_add(102))
         .set_frequency(9.25)
         .set_power(1.0 / 57.75)
         .set_roughness(6);
@@ -1080,10 +1120,10 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // roughly the same elevation. This BasicMulti module generates some
     // random values that will be used by subsequent noise functions to randomly
     // change the elevations of the mountain peaks.
-    let scaledMountainousTerrain_fb = Fbm::<Perlin>::new(CURRENT_SEED + 110)
+    let scaledMountainousTerrain_fb = Fbm::<Perlin>::new(config.seed.wrapping_add(110))
         .set_frequency(14.5)
         .set_persistence(0.5)
-        .set_lacunarity(MOUNTAIN_LACUNARITY)
+        .set_lacunarity(config.mountain_lacunarity)
         .set_octaves(6);
 
     // 3: [Peak-modulation module]: This exponential-curve module applies an
@@ -1146,10 +1186,10 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // roughly the same elevation. This BasicMulti module generates some
     // random values that will be used by subsequent noise functions to
     // randomly change the elevations of the hilltops.
-    let scaledHillyTerrain_fb = Fbm::<Perlin>::new(CURRENT_SEED + 120)
+    let scaledHillyTerrain_fb = Fbm::<Perlin>::new(config.seed.wrapping_add(120))
         .set_frequency(13.5)
         .set_persistence(0.5)
-        .set_lacunarity(HILLS_LACUNARITY)
+        .set_lacunarity(config.hills_lacunarity)
         .set_octaves(6);
 
     // 3: [Hilltop-modulation module]: This exponential-curve module applies an
@@ -1275,7 +1315,7 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     let continentalShelf_te = Terrace::new(&continentDef)
         .add_control_point(-1.0)
         .add_control_point(-0.75)
-        .add_control_point(SHELF_LEVEL)
+        .add_control_point(config.shelf_level)
         .add_control_point(1.0);
 
     //    debug::render_noise_module("complexplanet_images/18_0_continentalShelf_te\
@@ -1289,7 +1329,7 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // value from the shelf-creator module so that its possible range is from
     // the bottom of the ocean to sea level. This is done because this subgroup
     // is only concerned about the oceans.
-    let continentalShelf_cl = Clamp::new(continentalShelf_te).set_bounds(-0.75, SEA_LEVEL);
+    let continentalShelf_cl = Clamp::new(continentalShelf_te).set_bounds(-0.75, config.sea_level);
 
     //    debug::render_noise_module("complexplanet_images/18_1_continentalShelf_cl\
     //    .png",
@@ -1301,9 +1341,9 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // 3: [Oceanic-trench-basis module]: This ridged-multifractal-noise function
     // generates some coherent noise that will be used to generate the oceanic
     // trenches. The ridges represent the bottom of the trenches.
-    let continentalShelf_rm = RidgedMulti::<Perlin>::new(CURRENT_SEED + 130)
-        .set_frequency(CONTINENT_FREQUENCY * 4.375)
-        .set_lacunarity(CONTINENT_LACUNARITY)
+    let continentalShelf_rm = RidgedMulti::<Perlin>::new(config.seed.wrapping_add(130))
+        .set_frequency(config.continent_frequency * 4.375)
+        .set_lacunarity(config.continent_lacunarity)
         .set_octaves(16);
 
     //    debug::render_noise_module("complexplanet_images/18_2_continentalShelf_rm\
@@ -1357,7 +1397,7 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // scales the output value from the continent-definition group so that it
     // is measured in planetary elevation units.
     let baseContinentElev_sb = ScaleBias::new(&continentDef)
-        .set_scale(CONTINENT_HEIGHT_SCALE)
+        .set_scale(continent_height_scale)
         .set_bias(0.0);
 
     //    debug::render_noise_module("complexplanet_images/19_0_baseContinentElev_sb\
@@ -1375,7 +1415,7 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // selects the output value from the base-scaled-continent-elevations
     // module.
     let baseContinentElev_se = Select::new(baseContinentElev_sb, continentalShelf, &continentDef)
-        .set_bounds(SHELF_LEVEL - 1000.0, SHELF_LEVEL)
+        .set_bounds(config.shelf_level - 1000.0, config.shelf_level)
         .set_falloff(0.03125);
 
     // 3: [Base-continent-elevation subgroup]: Caches the output value from the
@@ -1447,7 +1487,7 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
         &continentsWithHills_ad,
         &terrainTypeDef,
     )
-    .set_bounds(1.0 - HILLS_AMOUNT, 1001.0 - HILLS_AMOUNT)
+    .set_bounds(1.0 - hills_amount, 1001.0 - hills_amount)
     .set_falloff(0.25);
 
     // 3: [Continents-with-hills subgroup]: Caches the output value from the
@@ -1491,7 +1531,7 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     let continentsWithMountains_cu = Curve::new(&continentDef)
         .add_control_point(-1.0, -0.0625)
         .add_control_point(0.0, 0.0000)
-        .add_control_point(1.0 - MOUNTAINS_AMOUNT, 0.0625)
+        .add_control_point(1.0 - config.mountains_amount, 0.0625)
         .add_control_point(1.0, 0.2500);
 
     //    debug::render_noise_module("complexplanet_images/22_1_continentsWithMountains_cu.png",
@@ -1524,7 +1564,7 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
         continentsWithMountains_ad1,
         &terrainTypeDef,
     )
-    .set_bounds(1.0 - MOUNTAINS_AMOUNT, 1001.0 - MOUNTAINS_AMOUNT)
+    .set_bounds(1.0 - config.mountains_amount, 1001.0 - config.mountains_amount)
     .set_falloff(0.25);
 
     // 5: [Continents-with-mountains subgroup]: Caches the output value from the
@@ -1551,10 +1591,10 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // 1: [Badlands-positions module]: This BasicMulti module generates some
     // random noise, which is used by subsequent noise functions to specify the
     // locations of the badlands.
-    let continentsWithBadlands_bm = Fbm::<Perlin>::new(CURRENT_SEED + 140)
+    let continentsWithBadlands_bm = Fbm::<Perlin>::new(config.seed.wrapping_add(140))
         .set_frequency(16.5)
         .set_persistence(0.5)
-        .set_lacunarity(CONTINENT_LACUNARITY)
+        .set_lacunarity(config.continent_lacunarity)
         .set_octaves(2);
 
     //    debug::render_noise_module("complexplanet_images/23_0_continentsWithBadlands_bm.png",
@@ -1588,7 +1628,7 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
         &continentsWithBadlands_ad,
         &continentsWithBadlands_bm,
     )
-    .set_bounds(1.0 - BADLANDS_AMOUNT, 1001.0 - BADLANDS_AMOUNT)
+    .set_bounds(1.0 - config.badlands_amount, 1001.0 - config.badlands_amount)
     .set_falloff(0.25);
 
     //    debug::render_noise_module("complexplanet_images/23_2_continentsWithBadlands_se.png",
@@ -1630,8 +1670,8 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     // from the river-positions group so that it is measured in planetary
     // elevation units and is negative; this is required for step 2.
     let continentsWithRivers_sb = ScaleBias::new(riverPositions)
-        .set_scale(RIVER_DEPTH / 2.0)
-        .set_bias(-RIVER_DEPTH / 2.0);
+        .set_scale(config.river_depth / 2.0)
+        .set_bias(-config.river_depth / 2.0);
 
     //    debug::render_noise_module("complexplanet_images/24_0_continentsWithRivers_sb.png",
     //                               &continentsWithRivers_sb,
@@ -1663,8 +1703,8 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
         continentsWithRivers_ad,
         &continentsWithBadlands,
     )
-    .set_bounds(SEA_LEVEL, CONTINENT_HEIGHT_SCALE + SEA_LEVEL)
-    .set_falloff(CONTINENT_HEIGHT_SCALE - SEA_LEVEL);
+    .set_bounds(config.sea_level, continent_height_scale + config.sea_level)
+    .set_falloff(continent_height_scale - config.sea_level);
 
     // 4: [Continents-with-rivers subgroup]: Caches the output value from the
     // blended-rivers-to-continents module.
@@ -1717,17 +1757,14 @@ pub fn make_planet() -> (NoiseMap, ColorGradient) {
     //        100000,
     //    );
 
-    let offset_x = 9.6;
-    let offset_y = 0.0;
-    let zoom = 3.0;
     let noise_map = PlaneMapBuilder::new(&unscaledFinalPlanet)
-        .set_size(256, 256)
-        .set_x_bounds((-2.0 + offset_x)/zoom, (2.0 + offset_y)/zoom)
-        .set_y_bounds((-2.0 + offset_x)/zoom, (2.0 + offset_y)/zoom)
+        .set_size(config.size, config.size)
+        .set_x_bounds(config.x_bounds.0, config.x_bounds.1)
+        .set_y_bounds(config.y_bounds.0, config.y_bounds.1)
         .build();
 
     let color_gradient = ColorGradient::new().build_terrain_gradient();
-    return (noise_map, color_gradient);
+    (noise_map, color_gradient)
 
     /*
     utils::write_image_to_file(
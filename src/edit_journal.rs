@@ -0,0 +1,312 @@
+use std::collections::{HashMap, VecDeque};
+
+/// Side in finest cells of the tiles the journal saves, or the whole map if it is smaller.
+const TILE_SIZE: usize = 16;
+
+/// Saved heights of one tile: everything needed to put it back.
+struct Tile {
+    /// Finest cell at the tile's corner.
+    x: usize,
+    z: usize,
+    size: usize,
+    heights: Vec<f64>,
+}
+
+/// One undo step: the state before an edit of every tile the edit touched.
+#[derive(Default)]
+struct Step {
+    tiles: Vec<Tile>,
+    /// Tile corner to index in `tiles`, so each tile is saved only once per step.
+    index: HashMap<(usize, usize), usize>,
+}
+
+impl Step {
+    fn bytes(&self) -> usize {
+        self.tiles.iter().map(|tile| tile.heights.len() * std::mem::size_of::<f64>()).sum()
+    }
+}
+
+/// Undo and redo history of a `HeightMap`, saved as sparse tiles of finest-level heights.
+///
+/// Each write is one step unless a stroke is open, in which case every write until the stroke
+/// ends goes into the same step.
+pub(crate) struct EditJournal {
+    undo: VecDeque<Step>,
+    redo: Vec<Step>,
+    /// The step being recorded.
+    open: Option<Step>,
+    in_stroke: bool,
+    max_steps: usize,
+    max_bytes: usize,
+    /// Size of `undo` in bytes of saved heights.
+    bytes: usize,
+}
+
+impl Default for EditJournal {
+    fn default() -> Self {
+        EditJournal {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            open: None,
+            in_stroke: false,
+            max_steps: 100,
+            max_bytes: 64 << 20,
+            bytes: 0,
+        }
+    }
+}
+
+impl EditJournal {
+    pub fn begin_stroke(&mut self) {
+        self.in_stroke = true;
+    }
+
+    pub fn end_stroke(&mut self) {
+        self.in_stroke = false;
+        if let Some(step) = self.open.take() {
+            self.commit(step);
+        }
+    }
+
+    /// Saves the tiles under finest cells `x0..x1` by `z0..z1` of a `map_size` map that the
+    /// current step has not saved yet, reading heights through `read`. Call before writing.
+    pub fn record<F: Fn(usize, usize) -> f64>(&mut self, map_size: usize, x0: usize, z0: usize, x1: usize, z1: usize, read: F) {
+        if self.max_steps == 0 {
+            return;
+        }
+        let mut step = self.open.take().unwrap_or_default();
+        let tile_size = TILE_SIZE.min(map_size);
+        for tz in (z0 / tile_size)..z1.div_ceil(tile_size) {
+            for tx in (x0 / tile_size)..x1.div_ceil(tile_size) {
+                let corner = (tx * tile_size, tz * tile_size);
+                if step.index.contains_key(&corner) {
+                    continue;
+                }
+                let mut heights = Vec::with_capacity(tile_size * tile_size);
+                for z in corner.1..corner.1 + tile_size {
+                    for x in corner.0..corner.0 + tile_size {
+                        heights.push(read(x, z));
+                    }
+                }
+                step.index.insert(corner, step.tiles.len());
+                step.tiles.push(Tile { x: corner.0, z: corner.1, size: tile_size, heights });
+            }
+        }
+        self.open = Some(step);
+    }
+
+    /// Ends the step `record` started, unless a stroke is open.
+    pub fn finish_write(&mut self) {
+        if !self.in_stroke {
+            if let Some(step) = self.open.take() {
+                self.commit(step);
+            }
+        }
+    }
+
+    fn commit(&mut self, step: Step) {
+        if step.tiles.is_empty() {
+            return;
+        }
+        self.redo.clear();
+        self.bytes += step.bytes();
+        self.undo.push_back(step);
+        self.enforce_limits();
+    }
+
+    /// Drops the oldest steps beyond the limits; the newest step is kept whatever its size,
+    /// unless no steps are allowed at all.
+    fn enforce_limits(&mut self) {
+        while self.undo.len() > self.max_steps || (self.bytes > self.max_bytes && self.undo.len() > 1) {
+            let step = self.undo.pop_front().unwrap();
+            self.bytes -= step.bytes();
+        }
+    }
+
+    pub fn set_limits(&mut self, max_steps: usize, max_bytes: usize) {
+        self.max_steps = max_steps;
+        self.max_bytes = max_bytes;
+        self.enforce_limits();
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.open = None;
+        self.in_stroke = false;
+        self.bytes = 0;
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Puts back the newest undo step through `swap`, which writes each tile's saved heights
+    /// and leaves the heights it replaced in their place, and keeps the result for redo.
+    pub fn undo<F: FnMut(usize, usize, usize, &mut [f64])>(&mut self, swap: F) -> bool {
+        self.end_stroke();
+        let mut step = match self.undo.pop_back() {
+            Some(step) => step,
+            None => return false,
+        };
+        self.bytes -= step.bytes();
+        swap_step(&mut step, swap);
+        self.redo.push(step);
+        true
+    }
+
+    /// Reapplies the newest undone step, the reverse of `undo`.
+    pub fn redo<F: FnMut(usize, usize, usize, &mut [f64])>(&mut self, swap: F) -> bool {
+        self.end_stroke();
+        let mut step = match self.redo.pop() {
+            Some(step) => step,
+            None => return false,
+        };
+        swap_step(&mut step, swap);
+        self.bytes += step.bytes();
+        self.undo.push_back(step);
+        self.enforce_limits();
+        true
+    }
+}
+
+fn swap_step<F: FnMut(usize, usize, usize, &mut [f64])>(step: &mut Step, mut swap: F) {
+    for tile in &mut step.tiles {
+        swap(tile.x, tile.z, tile.size, &mut tile.heights);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: usize = 32;
+
+    /// A bare grid of heights edited through a journal, standing in for a `HeightMap`.
+    struct Grid {
+        heights: Vec<f64>,
+        journal: EditJournal,
+    }
+
+    impl Grid {
+        fn new() -> Grid {
+            Grid { heights: vec![0.0; SIZE * SIZE], journal: EditJournal::default() }
+        }
+
+        fn write(&mut self, x0: usize, z0: usize, x1: usize, z1: usize, value: f64) {
+            let heights = &self.heights;
+            self.journal.record(SIZE, x0, z0, x1, z1, |x, z| heights[z * SIZE + x]);
+            for z in z0..z1 {
+                for x in x0..x1 {
+                    self.heights[z * SIZE + x] = value;
+                }
+            }
+            self.journal.finish_write();
+        }
+
+        fn swap(heights: &mut [f64], x: usize, z: usize, size: usize, saved: &mut [f64]) {
+            for dz in 0..size {
+                for dx in 0..size {
+                    std::mem::swap(&mut heights[(z + dz) * SIZE + x + dx], &mut saved[dz * size + dx]);
+                }
+            }
+        }
+
+        fn undo(&mut self) -> bool {
+            let heights = &mut self.heights;
+            self.journal.undo(|x, z, size, saved| Grid::swap(heights, x, z, size, saved))
+        }
+
+        fn redo(&mut self) -> bool {
+            let heights = &mut self.heights;
+            self.journal.redo(|x, z, size, saved| Grid::swap(heights, x, z, size, saved))
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_round_trip() {
+        let mut grid = Grid::new();
+        let mut states = vec![grid.heights.clone()];
+        grid.write(0, 0, 4, 4, 1.0);
+        states.push(grid.heights.clone());
+        grid.write(10, 12, 30, 20, 2.0);
+        states.push(grid.heights.clone());
+        grid.write(2, 2, 12, 14, 3.0);
+        states.push(grid.heights.clone());
+
+        for state in states.iter().rev().skip(1) {
+            assert!(grid.undo());
+            assert_eq!(&grid.heights, state);
+        }
+        assert!(!grid.undo());
+        for state in states.iter().skip(1) {
+            assert!(grid.redo());
+            assert_eq!(&grid.heights, state);
+        }
+        assert!(!grid.redo());
+    }
+
+    #[test]
+    fn strokes_group_writes_into_one_step() {
+        let mut grid = Grid::new();
+        grid.journal.begin_stroke();
+        grid.write(0, 0, 2, 2, 1.0);
+        grid.write(1, 1, 3, 3, 2.0);
+        grid.write(20, 20, 22, 22, 3.0);
+        grid.journal.end_stroke();
+        let stroked = grid.heights.clone();
+        assert_eq!(grid.journal.undo.len(), 1);
+
+        assert!(grid.undo());
+        assert!(grid.heights.iter().all(|&h| h == 0.0));
+        assert!(!grid.journal.can_undo());
+        assert!(grid.redo());
+        assert_eq!(grid.heights, stroked);
+    }
+
+    #[test]
+    fn new_write_clears_redo() {
+        let mut grid = Grid::new();
+        grid.write(0, 0, 2, 2, 1.0);
+        grid.write(0, 0, 2, 2, 2.0);
+        assert!(grid.undo());
+        assert!(grid.journal.can_redo());
+        grid.write(5, 5, 6, 6, 3.0);
+        assert!(!grid.journal.can_redo());
+        assert!(!grid.redo());
+    }
+
+    #[test]
+    fn limits_drop_the_oldest_steps() {
+        let mut grid = Grid::new();
+        grid.journal.set_limits(3, usize::MAX);
+        for i in 0..5 {
+            grid.write(i, 0, i + 1, 1, i as f64 + 1.0);
+        }
+        assert_eq!(grid.journal.undo.len(), 3);
+        while grid.undo() {}
+        // The two oldest writes can no longer be undone.
+        assert_eq!(&grid.heights[..5], &[1.0, 2.0, 0.0, 0.0, 0.0]);
+
+        // Every write saves one 16x16 tile; two fit in the byte limit.
+        let tile_bytes = TILE_SIZE * TILE_SIZE * std::mem::size_of::<f64>();
+        let mut grid = Grid::new();
+        grid.journal.set_limits(100, 2 * tile_bytes);
+        for i in 0..4 {
+            grid.write(0, 0, 1, 1, i as f64 + 1.0);
+        }
+        assert_eq!(grid.journal.undo.len(), 2);
+        assert_eq!(grid.journal.bytes, 2 * tile_bytes);
+        // The newest step is kept even when it alone is over the limit.
+        grid.journal.set_limits(100, 1);
+        assert_eq!(grid.journal.undo.len(), 1);
+        // No steps at all turns the history off.
+        grid.journal.set_limits(0, usize::MAX);
+        grid.write(0, 0, 1, 1, 9.0);
+        assert!(!grid.journal.can_undo());
+    }
+}
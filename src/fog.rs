@@ -0,0 +1,50 @@
+use crate::colour::lerp_rgb;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FogMode {
+    /// No fog before `start`, fully fogged from `end`, both in world units.
+    Linear { start: f64, end: f64 },
+    /// Fog amount `1 - e^(-density * distance)`.
+    Exponential { density: f64 },
+}
+
+pub struct FogConfig {
+    pub mode: FogMode,
+    pub colour: [u8; 3],
+    /// Fog thins out with altitude: the fog amount is scaled by `e^(-height_falloff * height)`.
+    /// Zero gives uniform fog.
+    pub height_falloff: f64,
+    /// Blends terrain towards a sky colour with distance before fog is applied.
+    pub aerial_perspective: Option<AerialPerspective>,
+}
+
+pub struct AerialPerspective {
+    pub sky_colour: [u8; 3],
+    /// Distance at which terrain is blended about 63% of the way to `sky_colour`.
+    pub distance: f64,
+}
+
+impl FogConfig {
+    /// Amount of fog in `0.0..=1.0` at horizontal distance `t` for terrain at `height`.
+    pub fn amount(&self, t: f64, height: f64) -> f64 {
+        let amount = match self.mode {
+            FogMode::Linear { start, end } => {
+                if end <= start {
+                    if t >= end { 1.0 } else { 0.0 }
+                } else {
+                    (t - start) / (end - start)
+                }
+            }
+            FogMode::Exponential { density } => 1.0 - (-density * t).exp(),
+        };
+        (amount * (-self.height_falloff * height.max(0.0)).exp()).clamp(0.0, 1.0)
+    }
+
+    pub fn apply(&self, colour: [u8; 3], t: f64, height: f64) -> [u8; 3] {
+        let mut colour = colour;
+        if let Some(aerial) = &self.aerial_perspective {
+            colour = lerp_rgb(colour, aerial.sky_colour, 1.0 - (-t / aerial.distance).exp());
+        }
+        lerp_rgb(colour, self.colour, self.amount(t, height))
+    }
+}
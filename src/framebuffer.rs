@@ -0,0 +1,146 @@
+use std::io::{self, Write};
+
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    stride: usize,
+    pixels: Vec<u32>,
+    /// Distance from the camera to the surface seen at each pixel, laid out like `pixels`.
+    depth: Option<Vec<f32>>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Framebuffer {
+        Framebuffer::with_stride(width, height, width)
+    }
+
+    pub fn with_stride(width: usize, height: usize, stride: usize) -> Framebuffer {
+        assert!(stride >= width, "stride ({}) must be at least the width ({})", stride, width);
+        Framebuffer {
+            width,
+            height,
+            stride,
+            pixels: vec![0; stride * height],
+            depth: None,
+        }
+    }
+
+    /// Starts recording depth on every render, with `f32::INFINITY` wherever sky or background
+    /// shows through.
+    pub fn enable_depth(&mut self) {
+        if self.depth.is_none() {
+            self.depth = Some(vec![f32::INFINITY; self.stride * self.height]);
+        }
+    }
+
+    pub fn disable_depth(&mut self) {
+        self.depth = None;
+    }
+
+    pub fn has_depth(&self) -> bool {
+        self.depth.is_some()
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    pub fn offset(&self, x: usize, y: usize) -> usize {
+        y * self.stride + x
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> u32 {
+        self.pixels[self.offset(x, y)]
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, colour: u32) {
+        let offset = self.offset(x, y);
+        self.pixels[offset] = colour;
+    }
+
+    /// World-space distance from the camera to the surface at (`x`, `y`); `f32::INFINITY` for
+    /// sky, or when depth is not enabled.
+    pub fn get_depth(&self, x: usize, y: usize) -> f32 {
+        match &self.depth {
+            Some(depth) => depth[self.offset(x, y)],
+            None => f32::INFINITY,
+        }
+    }
+
+    /// Does nothing unless depth is enabled.
+    pub fn set_depth(&mut self, x: usize, y: usize, distance: f32) {
+        let offset = self.offset(x, y);
+        if let Some(depth) = &mut self.depth {
+            depth[offset] = distance;
+        }
+    }
+
+    pub fn depth(&self) -> Option<&[f32]> {
+        self.depth.as_deref()
+    }
+
+    pub fn clear(&mut self, colour: u32) {
+        for y in 0..self.height {
+            let offset = self.offset(0, y);
+            for pixel in &mut self.pixels[offset..offset + self.width] {
+                *pixel = colour;
+            }
+            if let Some(depth) = &mut self.depth {
+                for distance in &mut depth[offset..offset + self.width] {
+                    *distance = f32::INFINITY;
+                }
+            }
+        }
+    }
+
+    pub fn pixels(&self) -> &[u32] {
+        &self.pixels
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [u32] {
+        &mut self.pixels
+    }
+
+    /// Pixels are stored as `0xAABBGGRR`, i.e. RGBA bytes in memory on little-endian targets.
+    fn rgb_bytes(&self, with_alpha: bool) -> Vec<u8> {
+        let channels = if with_alpha { 4 } else { 3 };
+        let mut data = Vec::with_capacity(self.width * self.height * channels);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let colour = self.get_pixel(x, y);
+                data.push(colour as u8);
+                data.push((colour >> 8) as u8);
+                data.push((colour >> 16) as u8);
+                if with_alpha {
+                    data.push((colour >> 24) as u8);
+                }
+            }
+        }
+        data
+    }
+
+    /// Writes the visible area as a binary (`P6`) PPM.
+    pub fn write_ppm<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+        writer.write_all(&self.rgb_bytes(false))
+    }
+
+    /// Writes the visible area as an RGBA PNG.
+    pub fn write_png<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(writer, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut png_writer = encoder.write_header()?;
+        png_writer.write_image_data(&self.rgb_bytes(true))?;
+        png_writer.finish()?;
+        Ok(())
+    }
+}
@@ -1,222 +1,492 @@
-use rand::prelude::random;
-use noise::{Fbm, Perlin};
-use noise::utils::{NoiseMapBuilder, PlaneMapBuilder, ColorGradient};
+use std::sync::atomic::{AtomicU64, Ordering};
 
-use crate::{QuadTree, Ray2, Vec2};
+use noise::utils::ColorGradient;
+
+use crate::edit_journal::EditJournal;
+use crate::{HeightMapError, HeightStats, PlanetConfig, QuadTree, Vec3};
+
+/// World-space width of one cell of the finest level.
+pub const BLOCK_SIZE: f64 = 40.0;
+
+/// Multiplier from stored height values to world-space heights.
+pub const HEIGHT_SCALE: f64 = 1000.0;
+
+/// Largest supported quadtree level count, i.e. maps of at most 32768x32768 cells.
+pub const MAX_LEVELS: usize = 16;
+
+pub(crate) fn scale_height(height: f64) -> f64 {
+    if height < 0.0 {
+        return 0.0;
+    }
+    height * HEIGHT_SCALE
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HeightFilter {
+    Nearest,
+    Bilinear,
+    Bicubic,
+}
 
 pub struct HeightMap {
     num_levels: usize,
-    quad_tree: QuadTree<f64>,
+    /// Min, max and mean of the finest cells under each node.
+    quad_tree: QuadTree<HeightStats>,
     color_gradient_op: Option<ColorGradient>,
-}
-
-pub struct TimeHeight {
-    pub t: f64,
-    pub height: f64,
+    /// Unit surface normal of every finest-level cell, row-major.
+    normals: Vec<[f32; 3]>,
+    /// Bumped by every edit, so caches built from the map can tell they are stale.
+    revision: u64,
+    /// Unique per map, so caches can tell two maps of the same size apart.
+    id: u64,
+    journal: EditJournal,
 }
 
 impl HeightMap {
+    /// Generates the default planet with `num_levels` quadtree levels, sampling the whole
+    /// planet window at `2^(num_levels-1)` cells a side. Panics unless `num_levels` is in
+    /// `1..=MAX_LEVELS`.
     pub fn new(num_levels: usize) -> HeightMap {
-        let mut r = HeightMap {
-            num_levels,
-            quad_tree: QuadTree::new(num_levels, 0.0f64),
-            color_gradient_op: None,
+        assert!((1..=MAX_LEVELS).contains(&num_levels), "num_levels must be in 1..={}, got {}", MAX_LEVELS, num_levels);
+        let config = PlanetConfig {
+            size: 1 << (num_levels-1),
+            ..PlanetConfig::default()
         };
-        r.init_data();
-        r
+        HeightMap::from_planet(&config).expect("size is a power of two within MAX_LEVELS")
     }
 
-    fn init_data(&mut self) {
-        let size = 1 << (self.num_levels-1);
-
-        let (noise_map, color_gradient) = crate::make_planet();
-        self.color_gradient_op = Some(color_gradient);
+    /// Generates a map covering the whole of `config`'s noise map, which must be a power of two
+    /// in size.
+    pub fn from_planet(config: &PlanetConfig) -> Result<HeightMap, HeightMapError> {
+        if !config.size.is_power_of_two() {
+            return Err(HeightMapError::NotPowerOfTwo { size: config.size });
+        }
+        let max = 1 << (MAX_LEVELS - 1);
+        if config.size > max {
+            return Err(HeightMapError::TooLarge { size: config.size, max });
+        }
+        let (noise_map, color_gradient) = crate::make_planet(config);
+        let mut heights = Vec::with_capacity(config.size * config.size);
+        for y in 0..config.size {
+            for x in 0..config.size {
+                heights.push(noise_map.get_value(x, y));
+            }
+        }
+        Ok(HeightMap::from_grid(config.size, &heights, Some(color_gradient)))
+    }
 
-        /*
-        let fbm = Fbm::<Perlin>::new(0);
-        let noise_map = PlaneMapBuilder::<_, 2>::new(&fbm)
-                .set_size(size, size)
-                .set_x_bounds(-2.0, 2.0)
-                .set_y_bounds(-2.0, 2.0)
-                .build();
-        let color_gradient = ColorGradient::new().build_terrain_gradient();
-        self.color_gradient_op = Some(color_gradient);
-        */
-        
+    /// Builds a map from a square, power-of-two grid of finest-level heights in row-major order.
+    pub(crate) fn from_grid(size: usize, heights: &[f64], color_gradient_op: Option<ColorGradient>) -> HeightMap {
+        debug_assert!(size.is_power_of_two());
+        debug_assert_eq!(heights.len(), size * size);
+        let num_levels = size.trailing_zeros() as usize + 1;
+        let mut r = HeightMap {
+            num_levels,
+            quad_tree: QuadTree::new(num_levels, HeightStats::new(0.0)),
+            color_gradient_op,
+            normals: Vec::new(),
+            revision: 0,
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            journal: EditJournal::default(),
+        };
         for y in 0..size {
             for x in 0..size {
-                //let h: f64 = random::<f64>() * 80.0 - 140.0;
-                //let h = ((x as f64) * 0.1).cos() * ((y as f64) * 0.1).sin() * 40.0 - 100.0;
-                let h = noise_map.get_value(x, y);
-                self.quad_tree.set_value(self.num_levels-1, x, y, h);
+                r.quad_tree.set_value(num_levels-1, x, y, HeightStats::new(heights[y * size + x]));
             }
         }
+        r.rebuild_pyramid();
+        r.compute_normals();
+        r
+    }
+
+    /// Recomputes the stats of every coarser level from its four children.
+    fn rebuild_pyramid(&mut self) {
+        let size = self.size();
+        self.update_pyramid(0, 0, size, size);
+    }
+
+    /// Recomputes the stats of the ancestors of finest cells `x0..x1` by `z0..z1`, each once,
+    /// from their four children.
+    fn update_pyramid(&mut self, x0: usize, z0: usize, x1: usize, z1: usize) {
+        let (mut x0, mut z0, mut x1, mut z1) = (x0, z0, x1, z1);
         for lvl in (0..self.num_levels-1).rev() {
             let lvl2 = lvl + 1;
-            let size = 1 << lvl;
-            for y in 0..size {
-                for x in 0..size {
+            x0 >>= 1;
+            z0 >>= 1;
+            x1 = (x1 + 1) >> 1;
+            z1 = (z1 + 1) >> 1;
+            for y in z0..z1 {
+                for x in x0..x1 {
                     let xx = x << 1;
                     let yy = y << 1;
-                    let h1 = self.quad_tree.get_value(lvl2, xx, yy);
-                    let h2 = self.quad_tree.get_value(lvl2, xx+1, yy);
-                    let h3 = self.quad_tree.get_value(lvl2, xx+1, yy+1);
-                    let h4 = self.quad_tree.get_value(lvl2, xx, yy+1);
-                    let h = h1.max(*h2).max(h3.max(*h4));
-                    self.quad_tree.set_value(lvl, x, y, h);
+                    let h1 = *self.quad_tree.get_value(lvl2, xx, yy);
+                    let h2 = *self.quad_tree.get_value(lvl2, xx+1, yy);
+                    let h3 = *self.quad_tree.get_value(lvl2, xx+1, yy+1);
+                    let h4 = *self.quad_tree.get_value(lvl2, xx, yy+1);
+                    self.quad_tree.set_value(lvl, x, y, HeightStats::merge([h1, h2, h3, h4]));
                 }
             }
         }
     }
 
+    /// Recomputes the cached normals from central differences of the rendered (scaled)
+    /// heights, so flattened seas stay flat.
+    fn compute_normals(&mut self) {
+        let size = self.size();
+        self.normals = vec![[0.0, 1.0, 0.0]; size * size];
+        self.update_normals(0, 0, size, size);
+    }
+
+    /// Recomputes the cached normals of finest cells `x0..x1` by `z0..z1`.
+    fn update_normals(&mut self, x0: usize, z0: usize, x1: usize, z1: usize) {
+        let size = self.size();
+        let level = self.num_levels-1;
+        let quad_tree = &self.quad_tree;
+        let height = |x: usize, z: usize| scale_height(quad_tree.get_value(level, x, z).max);
+        for z in z0..z1 {
+            for x in x0..x1 {
+                let x0 = x.saturating_sub(1);
+                let x1 = (x + 1).min(size - 1);
+                let z0 = z.saturating_sub(1);
+                let z1 = (z + 1).min(size - 1);
+                let dx = (height(x1, z) - height(x0, z)) / (((x1 - x0).max(1) as f64) * BLOCK_SIZE);
+                let dz = (height(x, z1) - height(x, z0)) / (((z1 - z0).max(1) as f64) * BLOCK_SIZE);
+                let n = Vec3::new(-dx, 1.0, -dz).normalize();
+                self.normals[z * size + x] = [n.x as f32, n.y as f32, n.z as f32];
+            }
+        }
+    }
+
+    /// Unit surface normal of a finest-level cell.
+    pub fn normal(&self, x: usize, z: usize) -> Vec3<f64> {
+        let n = self.normals[z * self.size() + x];
+        Vec3::new(n[0] as f64, n[1] as f64, n[2] as f64)
+    }
+
+    pub fn num_levels(&self) -> usize {
+        self.num_levels
+    }
+
+    /// Width (and height) in cells of the finest level.
+    pub fn size(&self) -> usize {
+        1 << (self.num_levels-1)
+    }
+
+    pub fn color_gradient(&self) -> Option<&ColorGradient> {
+        self.color_gradient_op.as_ref()
+    }
+
+    /// Sets every finest cell under a node to `val`, then updates the node's ancestors and the
+    /// normals around it. At the finest level this sets a single cell; at the root, the whole
+    /// map. Either way the write is one undo step.
     pub fn write(&mut self, level: usize, x: usize, y: usize, val: f64) {
-        self.quad_tree.set_value(level, x, y, val);
+        assert!(level < self.num_levels, "level {} is outside a map with {} levels", level, self.num_levels);
+        let shift = self.num_levels-1 - level;
+        self.fill_region(x << shift, y << shift, 1 << shift, 1 << shift, |_, _| val);
     }
 
-    pub fn read(&self, level: usize, x: usize, y: usize) -> f64 {
-        *self.quad_tree.get_value(level, x, y)
+    /// Sets the `width` by `height` finest cells from (`x`, `y`) to `heights`, given row by row,
+    /// then updates the pyramid above them and the normals around them once. The cells'
+    /// previous heights go into the undo history.
+    pub fn write_region(&mut self, x: usize, y: usize, width: usize, height: usize, heights: &[f64]) {
+        assert_eq!(width.checked_mul(height), Some(heights.len()), "expected {}x{} heights, got {}", width, height, heights.len());
+        self.fill_region(x, y, width, height, |dx, dy| heights[dy * width + dx]);
     }
 
-    pub fn ray_xz_intersection_2pt5d<Callback: FnMut(TimeHeight,bool,Option<[u8;4]>)->bool>(&self, ray_xz: Ray2<f64>, mut callback: Callback) {
-        const BLOCK_SIZE: f64 = 40.0;
-        let size: usize = 1 << (self.num_levels-1);
-        let size2 = (size as f64) * BLOCK_SIZE;
-        let t1 = (-0.5 * size2 - ray_xz.origin.x) / ray_xz.direction.x;
-        let t2 = (0.5 * size2 - ray_xz.origin.x) / ray_xz.direction.x;
-        let t3 = (-0.5 * size2 - ray_xz.origin.y) / ray_xz.direction.y;
-        let t4 = (0.5 * size2 - ray_xz.origin.y) / ray_xz.direction.y;
-        let t_min = t1.min(t2).max(t3.min(t4));
-        let t_max = t1.max(t2).min(t3.max(t4));
-        fn scale_height(height: f64) -> f64 {
-            if height < 0.0 {
-                return 0.0;
-            }
-            return height * 1000.0;
-        }
-        if t_max < t_min {
+    fn fill_region<F: Fn(usize, usize) -> f64>(&mut self, x: usize, y: usize, width: usize, height: usize, value: F) {
+        let size = self.size();
+        assert!(
+            x.checked_add(width).is_some_and(|x1| x1 <= size) && y.checked_add(height).is_some_and(|y1| y1 <= size),
+            "region {}x{} at ({}, {}) is outside the {}x{} map",
+            width, height, x, y, size, size
+        );
+        if width == 0 || height == 0 {
             return;
         }
-        let pos_xz: Vec2<f64>;
-        if t_min >= 0.0 {
-            pos_xz = ray_xz.position_from_time(t_min) + Vec2::new(0.5 * size2, 0.5 * size2);
-        } else {
-            pos_xz = ray_xz.origin + Vec2::new(0.5 * size2, 0.5 * size2);
-        }
-        let mut map_x = (pos_xz.x / BLOCK_SIZE) as i32;
-        let mut map_z = (pos_xz.y / BLOCK_SIZE) as i32;
-        let mut side_dist_x: f64;
-        let mut side_dist_z: f64;
-        let delta_dist_x = (BLOCK_SIZE / ray_xz.direction.x).abs();
-        let delta_dist_z = (BLOCK_SIZE / ray_xz.direction.y).abs();
-        let step_x: i32;
-        let step_z: i32;
-        
-        if ray_xz.direction.x < 0.0 {
-            step_x = -1;
-            side_dist_x = (pos_xz.x / BLOCK_SIZE - (map_x as f64)) * delta_dist_x;
-        } else {
-            step_x = 1;
-            side_dist_x = (((map_x + 1) as f64) - pos_xz.x / BLOCK_SIZE) * delta_dist_x;
+        let fine = self.num_levels-1;
+        let quad_tree = &self.quad_tree;
+        self.journal.record(size, x, y, x + width, y + height, |cx, cz| quad_tree.get_value(fine, cx, cz).max);
+        for dy in 0..height {
+            for dx in 0..width {
+                self.quad_tree.set_value(fine, x + dx, y + dy, HeightStats::new(value(dx, dy)));
+            }
         }
-        if ray_xz.direction.y < 0.0 {
-            step_z = -1;
-            side_dist_z = (pos_xz.y / BLOCK_SIZE - (map_z as f64)) * delta_dist_z;
-        } else {
-            step_z = 1;
-            side_dist_z = (((map_z + 1) as f64) - pos_xz.y / BLOCK_SIZE) * delta_dist_z;
+        self.journal.finish_write();
+        self.region_changed(x, y, x + width, y + height);
+    }
+
+    /// Brings the pyramid, normals and revision up to date after finest cells `x0..x1` by
+    /// `z0..z1` have changed.
+    fn region_changed(&mut self, x0: usize, z0: usize, x1: usize, z1: usize) {
+        let size = self.size();
+        self.update_pyramid(x0, z0, x1, z1);
+        // Normals come from central differences, so the cells bordering the region change too.
+        self.update_normals(x0.saturating_sub(1), z0.saturating_sub(1), (x1 + 1).min(size), (z1 + 1).min(size));
+        self.revision += 1;
+    }
+
+    /// Groups every write until `end_stroke` into a single undo step, e.g. all the dabs of one
+    /// brush stroke.
+    pub fn begin_stroke(&mut self) {
+        self.journal.begin_stroke();
+    }
+
+    pub fn end_stroke(&mut self) {
+        self.journal.end_stroke();
+    }
+
+    /// Reverts the newest undo step, ending any open stroke first. Returns false if there was
+    /// nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let mut journal = std::mem::take(&mut self.journal);
+        let done = journal.undo(|x, z, size, heights| self.swap_tile(x, z, size, heights));
+        self.journal = journal;
+        done
+    }
+
+    /// Reapplies the newest undone step. Any new write discards everything that could be redone.
+    pub fn redo(&mut self) -> bool {
+        let mut journal = std::mem::take(&mut self.journal);
+        let done = journal.redo(|x, z, size, heights| self.swap_tile(x, z, size, heights));
+        self.journal = journal;
+        done
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.journal.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.journal.can_redo()
+    }
+
+    /// Keeps at most `max_steps` undo steps, dropping the oldest ones first, and also drops old
+    /// steps while the history holds more than `max_bytes` of saved heights. No steps at all
+    /// turns the history off. Defaults to 100 steps and 64 MiB.
+    pub fn set_history_limit(&mut self, max_steps: usize, max_bytes: usize) {
+        self.journal.set_limits(max_steps, max_bytes);
+    }
+
+    pub fn clear_history(&mut self) {
+        self.journal.clear();
+    }
+
+    /// Exchanges the `size` by `size` finest cells from (`x`, `z`) with `heights`.
+    fn swap_tile(&mut self, x: usize, z: usize, size: usize, heights: &mut [f64]) {
+        let fine = self.num_levels-1;
+        for dz in 0..size {
+            for dx in 0..size {
+                let saved = &mut heights[dz * size + dx];
+                let current = self.quad_tree.get_value(fine, x + dx, z + dz).max;
+                self.quad_tree.set_value(fine, x + dx, z + dz, HeightStats::new(*saved));
+                *saved = current;
+            }
         }
-        loop {
-            let dist: f64;
-            dist = t_min.max(0.0) + side_dist_x.min(side_dist_z);
-            if side_dist_x < side_dist_z {
-                side_dist_x += delta_dist_x;
-                map_x += step_x;
-            } else {
-                side_dist_z += delta_dist_z;
-                map_z += step_z;
+        self.region_changed(x, z, x + size, z + size);
+    }
+
+    /// Changes after every write; compare to tell whether something built from the map, such as
+    /// a `ShadowMap`, is out of date.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Identifies this map among every map created by the process; with `revision`, tells a
+    /// cache which map, in which state, it was built from.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Max of the finest-level heights under a node; the height itself at the finest level.
+    pub fn read(&self, level: usize, x: usize, y: usize) -> f64 {
+        self.quad_tree.get_value(level, x, y).max
+    }
+
+    /// Mean of the finest-level heights under a node; the height itself at the finest level.
+    pub fn read_mean(&self, level: usize, x: usize, y: usize) -> f64 {
+        self.quad_tree.get_value(level, x, y).mean
+    }
+
+    pub fn read_stats(&self, level: usize, x: usize, y: usize) -> HeightStats {
+        *self.quad_tree.get_value(level, x, y)
+    }
+
+    /// Min, max and mean over the whole map.
+    pub fn stats(&self) -> HeightStats {
+        self.read_stats(0, 0, 0)
+    }
+
+    /// Samples the finest level at world position (`x`, `z`), returning a world-space height.
+    /// Positions outside the map are clamped to the edge cells.
+    pub fn sample(&self, x: f64, z: f64, filter: HeightFilter) -> f64 {
+        scale_height(self.sample_raw(x, z, filter))
+    }
+
+    pub(crate) fn sample_raw(&self, x: f64, z: f64, filter: HeightFilter) -> f64 {
+        let size: usize = 1 << (self.num_levels-1);
+        let half_size2 = 0.5 * (size as f64) * BLOCK_SIZE;
+        // Grid coordinates with cell centres on whole numbers.
+        let gx = (x + half_size2) / BLOCK_SIZE - 0.5;
+        let gz = (z + half_size2) / BLOCK_SIZE - 0.5;
+        let cell = |cx: i64, cz: i64| -> f64 {
+            let cx = cx.clamp(0, size as i64 - 1) as usize;
+            let cz = cz.clamp(0, size as i64 - 1) as usize;
+            self.read(self.num_levels-1, cx, cz)
+        };
+        match filter {
+            HeightFilter::Nearest => cell(gx.round() as i64, gz.round() as i64),
+            HeightFilter::Bilinear => {
+                let x0 = gx.floor();
+                let z0 = gz.floor();
+                let fx = gx - x0;
+                let fz = gz - z0;
+                let x0 = x0 as i64;
+                let z0 = z0 as i64;
+                let h0 = cell(x0, z0) + (cell(x0 + 1, z0) - cell(x0, z0)) * fx;
+                let h1 = cell(x0, z0 + 1) + (cell(x0 + 1, z0 + 1) - cell(x0, z0 + 1)) * fx;
+                h0 + (h1 - h0) * fz
             }
-            if 0 <= map_x && map_x < size as i32 {
-                if 0 <= map_z && map_z < size as i32 {
-                    let height = self.read(self.num_levels-1, map_x as usize, map_z as usize);
-                    let color: Option<[u8;4]>;
-                    if let Some(color_gradient) = &self.color_gradient_op {
-                        color = Some(color_gradient.get_color(height));
-                    } else {
-                        color = None;
-                    }
-                    let _ = callback(TimeHeight { t: dist, height: scale_height(height), }, false, color);
+            HeightFilter::Bicubic => {
+                // Catmull-Rom through the 4x4 neighbourhood.
+                fn cubic(p0: f64, p1: f64, p2: f64, p3: f64, t: f64) -> f64 {
+                    p1 + 0.5 * t * (p2 - p0 + t * (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3 + t * (3.0 * (p1 - p2) + p3 - p0)))
                 }
+                let x0 = gx.floor();
+                let z0 = gz.floor();
+                let fx = gx - x0;
+                let fz = gz - z0;
+                let x0 = x0 as i64;
+                let z0 = z0 as i64;
+                let mut rows = [0.0; 4];
+                for (i, row) in rows.iter_mut().enumerate() {
+                    let cz = z0 - 1 + i as i64;
+                    *row = cubic(cell(x0 - 1, cz), cell(x0, cz), cell(x0 + 1, cz), cell(x0 + 2, cz), fx);
+                }
+                cubic(rows[0], rows[1], rows[2], rows[3], fz)
             }
-            if map_x < 0 && step_x < 0 {
-                break;
-            }
-            if map_x >= size as i32 && step_x > 0 {
-                break;
-            }
-            if map_z < 0 && step_z < 0 {
-                break;
-            }
-            if map_z >= size as i32 && step_z > 0 {
-                break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn height_map() -> HeightMap {
+        let size = 16;
+        let heights: Vec<f64> = (0..size * size).map(|i| ((i * 7919) % 101) as f64 / 50.0 - 1.0).collect();
+        HeightMap::from_grid(size, &heights, None)
+    }
+
+    /// Checks every node's stats against the finest cells under it.
+    fn assert_stats_consistent(height_map: &HeightMap) {
+        let fine = height_map.num_levels()-1;
+        for level in 0..=fine {
+            let shift = fine - level;
+            for z in 0..1 << level {
+                for x in 0..1 << level {
+                    let mut cells = Vec::new();
+                    for cz in z << shift..(z + 1) << shift {
+                        for cx in x << shift..(x + 1) << shift {
+                            cells.push(height_map.read(fine, cx, cz));
+                        }
+                    }
+                    let stats = height_map.read_stats(level, x, z);
+                    assert_eq!(stats.min, cells.iter().copied().fold(f64::INFINITY, f64::min), "min at level {} ({}, {})", level, x, z);
+                    assert_eq!(stats.max, cells.iter().copied().fold(f64::NEG_INFINITY, f64::max), "max at level {} ({}, {})", level, x, z);
+                    let mean = cells.iter().sum::<f64>() / cells.len() as f64;
+                    assert!((stats.mean - mean).abs() < 1.0e-12, "mean at level {} ({}, {})", level, x, z);
+                }
             }
         }
     }
 
-    pub fn ray_xz_insection_2pt5d<Callback: FnMut(TimeHeight,bool,Option<[u8;4]>)->bool>(&self, ray_xz: Ray2<f64>, mut callback: Callback) {
-        self.ray_xz_insection_2pt5d_2(0, 0, 0, ray_xz, &mut callback)
+    #[test]
+    fn stats_stay_consistent_after_writes() {
+        let mut height_map = height_map();
+        assert_stats_consistent(&height_map);
+        height_map.write(2, 1, 3, 0.75);
+        assert_stats_consistent(&height_map);
+        height_map.write(4, 5, 13, -0.9);
+        height_map.write(4, 15, 0, 2.0);
+        assert_stats_consistent(&height_map);
+        height_map.write(0, 0, 0, 0.25);
+        assert_stats_consistent(&height_map);
+        assert_eq!(height_map.stats(), HeightStats::new(0.25));
+    }
+
+    #[test]
+    #[should_panic(expected = "outside the 16x16 map")]
+    fn rejects_regions_that_overflow() {
+        height_map().write_region(usize::MAX, 0, 2, 1, &[0.0, 0.0]);
     }
 
-    fn ray_xz_insection_2pt5d_2<CALLBACK: FnMut(TimeHeight,bool,Option<[u8;4]>)->bool>(&self, depth: usize, x0: usize, y0: usize, ray_xz: Ray2<f64>, callback: &mut CALLBACK) {
-        let size: usize = 1 << (self.num_levels-1-depth);
-        let size2 = (size as f64) * 40.0;//* 2.0;
-        let t1 = (-0.5 * size2 - ray_xz.origin.x) / ray_xz.direction.x;
-        let t2 = (0.5 * size2 - ray_xz.origin.x) / ray_xz.direction.x;
-        let t3 = (-0.5 * size2 - ray_xz.origin.y) / ray_xz.direction.y;
-        let t4 = (0.5 * size2 - ray_xz.origin.y) / ray_xz.direction.y;
-        let t_min = t1.min(t2).max(t3.min(t4));
-        let t_max = t1.max(t2).min(t3.max(t4));
-        fn scale_height(height: f64) -> f64 {
-            if height < 0.0 {
-                return 0.0;
+    #[test]
+    fn coarse_writes_fill_the_node_subtree() {
+        let mut height_map = height_map();
+        let before: Vec<f64> = (0..16 * 16).map(|i| height_map.read(4, i % 16, i / 16)).collect();
+        // Level 2 nodes cover 4x4 finest cells.
+        height_map.write(2, 1, 2, 0.5);
+        for z in 0..16 {
+            for x in 0..16 {
+                let expected = if (4..8).contains(&x) && (8..12).contains(&z) { 0.5 } else { before[z * 16 + x] };
+                assert_eq!(height_map.read(4, x, z), expected, "cell ({}, {})", x, z);
             }
-            return height * 1000.0;
         }
-        if t_max < t_min {
-            return;
+        assert_eq!(height_map.read_stats(2, 1, 2), HeightStats::new(0.5));
+        assert!(height_map.undo());
+        assert!(!height_map.can_undo());
+        assert!((0..16 * 16).all(|i| height_map.read(4, i % 16, i / 16) == before[i]));
+    }
+
+    #[test]
+    #[should_panic(expected = "outside a map with 5 levels")]
+    fn rejects_writes_below_the_finest_level() {
+        height_map().write(5, 0, 0, 0.0);
+    }
+
+    #[test]
+    fn undo_and_redo_refresh_stats_normals_and_revision() {
+        let normal_at = |height_map: &HeightMap| {
+            let n = height_map.normal(6, 6);
+            (n.x, n.y, n.z)
+        };
+        let mut height_map = height_map();
+        let stats = height_map.stats();
+        let normal = normal_at(&height_map);
+        let revision = height_map.revision();
+
+        height_map.write_region(5, 5, 2, 2, &[3.0, 3.0, 3.0, 3.0]);
+        let edited_stats = height_map.stats();
+        let edited_normal = normal_at(&height_map);
+        assert_eq!(edited_stats.max, 3.0);
+        assert_ne!(edited_normal, normal);
+
+        assert!(height_map.undo());
+        assert_eq!(height_map.read_stats(0, 0, 0), stats);
+        assert_eq!(normal_at(&height_map), normal);
+        assert!(height_map.revision() > revision + 1);
+        let undone_revision = height_map.revision();
+
+        assert!(height_map.redo());
+        assert_eq!(height_map.read_stats(0, 0, 0), edited_stats);
+        assert_eq!(normal_at(&height_map), edited_normal);
+        assert!(height_map.revision() > undone_revision);
+    }
+
+    #[test]
+    fn saturated_history_limit_keeps_every_step() {
+        let mut height_map = height_map();
+        height_map.set_history_limit(usize::MAX, usize::MAX);
+        for i in 0..200 {
+            height_map.write(4, i % 16, i / 16, 1.0);
         }
-        if depth < self.num_levels-1 {
-            let height = self.read(depth, x0 >> (self.num_levels-1-depth), y0 >> (self.num_levels-1-depth));
-            if callback(TimeHeight { t: t_max, height: scale_height(height) }, true, None) {
-                return;
-            }
-            let half_size = size >> 1;
-            let mut offsets: [([f64; 2],[usize; 2]); 4] = [
-                ([0.25 * size2, 0.25 * size2], [x0, y0]),
-                ([-0.25 * size2, 0.25 * size2], [x0+half_size, y0+0]),
-                ([-0.25 * size2, -0.25 * size2], [x0+half_size, y0+half_size]),
-                ([0.25 * size2, -0.25 * size2], [x0, y0+half_size]),
-            ];
-            offsets.sort_by(|a, b| {
-                let x = -a.0[0] * ray_xz.direction.x - a.0[1] * ray_xz.direction.y;
-                let y = -b.0[0] * ray_xz.direction.x - b.0[1] * ray_xz.direction.y;
-                return x.partial_cmp(&y).unwrap();
-            });
-            for (ray_offset, index_offset) in offsets {
-                let ray_xz2 = Ray2::new(ray_xz.origin + Vec2::new(ray_offset[0], ray_offset[1]), ray_xz.direction);
-                self.ray_xz_insection_2pt5d_2(depth + 1, index_offset[0], index_offset[1], ray_xz2, callback);
-            }
-        } else {
-            if t_min <= 0.0 {
-                return;
-            }
-            let height = self.read(depth, x0, y0);
-            let color: Option<[u8;4]>;
-            if let Some(color_gradient) = &self.color_gradient_op {
-                color = Some(color_gradient.get_color(height));
-            } else {
-                color = None;
-            }
-            let _ = callback(TimeHeight { t: t_max, height: scale_height(height), }, false, color);
+        let mut steps = 0;
+        while height_map.undo() {
+            steps += 1;
         }
+        assert_eq!(steps, 200);
     }
 }
@@ -0,0 +1,414 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use noise::utils::ColorGradient;
+
+use crate::{HeightMap, MAX_LEVELS};
+
+/// Byte order of raw 16-bit height samples.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+#[derive(Debug)]
+pub enum HeightMapError {
+    Io(io::Error),
+    Png(png::DecodingError),
+    PngEncoding(png::EncodingError),
+    /// A colourised export was requested but the map has no colour gradient.
+    MissingColorGradient,
+    /// The image is not square; `QuadTree` levels always are.
+    NotSquare { width: usize, height: usize },
+    NotPowerOfTwo { size: usize },
+    /// The map needs more than `MAX_LEVELS` quadtree levels.
+    TooLarge { size: usize, max: usize },
+    /// The input ended early; counts are bytes for binary formats and samples for ASCII PGM.
+    Truncated { expected: usize, actual: usize },
+    UnsupportedBitDepth(u32),
+    InvalidFormat(String),
+}
+
+impl fmt::Display for HeightMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeightMapError::Io(err) => write!(f, "i/o error: {}", err),
+            HeightMapError::Png(err) => write!(f, "png error: {}", err),
+            HeightMapError::PngEncoding(err) => write!(f, "png encoding error: {}", err),
+            HeightMapError::MissingColorGradient => write!(f, "height map has no colour gradient"),
+            HeightMapError::NotSquare { width, height } => {
+                write!(f, "height map must be square, got {}x{}", width, height)
+            }
+            HeightMapError::NotPowerOfTwo { size } => {
+                write!(f, "height map size must be a power of two, got {}", size)
+            }
+            HeightMapError::TooLarge { size, max } => {
+                write!(f, "height map size {} exceeds the maximum of {}", size, max)
+            }
+            HeightMapError::Truncated { expected, actual } => {
+                write!(f, "truncated height data: expected {}, got {}", expected, actual)
+            }
+            HeightMapError::UnsupportedBitDepth(bits) => write!(f, "unsupported bit depth: {}", bits),
+            HeightMapError::InvalidFormat(msg) => write!(f, "invalid format: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for HeightMapError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HeightMapError::Io(err) => Some(err),
+            HeightMapError::Png(err) => Some(err),
+            HeightMapError::PngEncoding(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for HeightMapError {
+    fn from(err: io::Error) -> Self {
+        HeightMapError::Io(err)
+    }
+}
+
+impl From<png::DecodingError> for HeightMapError {
+    fn from(err: png::DecodingError) -> Self {
+        HeightMapError::Png(err)
+    }
+}
+
+impl From<png::EncodingError> for HeightMapError {
+    fn from(err: png::EncodingError) -> Self {
+        HeightMapError::PngEncoding(err)
+    }
+}
+
+/// Maps an integer sample in `0..=max_value` onto the `-1.0..=1.0` range `make_planet` produces.
+fn sample_to_height(value: u32, max_value: u32) -> f64 {
+    (value as f64) / (max_value as f64) * 2.0 - 1.0
+}
+
+/// Inverse of `sample_to_height` for 16-bit samples; heights outside `-1.0..=1.0` are clamped.
+fn height_to_sample(height: f64) -> u16 {
+    ((height + 1.0) * 0.5 * 65535.0).round().clamp(0.0, 65535.0) as u16
+}
+
+fn check_size(width: usize, height: usize) -> Result<usize, HeightMapError> {
+    if width != height {
+        return Err(HeightMapError::NotSquare { width, height });
+    }
+    if !width.is_power_of_two() {
+        return Err(HeightMapError::NotPowerOfTwo { size: width });
+    }
+    let max = 1 << (MAX_LEVELS - 1);
+    if width > max {
+        return Err(HeightMapError::TooLarge { size: width, max });
+    }
+    Ok(width)
+}
+
+fn read_all<R: Read>(mut reader: R) -> Result<Vec<u8>, HeightMapError> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+fn heights_from_samples(
+    data: &[u8],
+    size: usize,
+    bytes_per_sample: usize,
+    max_value: u32,
+    endianness: Endianness,
+) -> Result<Vec<f64>, HeightMapError> {
+    let expected = size
+        .checked_mul(size)
+        .and_then(|n| n.checked_mul(bytes_per_sample))
+        .ok_or(HeightMapError::TooLarge { size, max: 1 << (MAX_LEVELS - 1) })?;
+    if data.len() < expected {
+        return Err(HeightMapError::Truncated { expected, actual: data.len() });
+    }
+    let heights = data[..expected]
+        .chunks_exact(bytes_per_sample)
+        .map(|bytes| {
+            let value = match (bytes_per_sample, endianness) {
+                (1, _) => bytes[0] as u32,
+                (_, Endianness::Big) => u16::from_be_bytes([bytes[0], bytes[1]]) as u32,
+                (_, Endianness::Little) => u16::from_le_bytes([bytes[0], bytes[1]]) as u32,
+            };
+            sample_to_height(value.min(max_value), max_value)
+        })
+        .collect();
+    Ok(heights)
+}
+
+/// Splits the ASCII header of a PGM file into its tokens, skipping `#` comments. Returns the
+/// tokens and the offset just past the single whitespace byte that ends the header.
+fn pgm_header(data: &[u8], num_tokens: usize) -> Result<(Vec<String>, usize), HeightMapError> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while tokens.len() < num_tokens {
+        if pos >= data.len() {
+            return Err(HeightMapError::InvalidFormat("truncated PGM header".to_string()));
+        }
+        let c = data[pos];
+        if c == b'#' {
+            while pos < data.len() && data[pos] != b'\n' {
+                pos += 1;
+            }
+        } else if c.is_ascii_whitespace() {
+            pos += 1;
+        } else {
+            let start = pos;
+            while pos < data.len() && !data[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            tokens.push(String::from_utf8_lossy(&data[start..pos]).into_owned());
+        }
+    }
+    Ok((tokens, pos + 1))
+}
+
+fn parse_pgm_number(token: &str) -> Result<usize, HeightMapError> {
+    token.parse().map_err(|_| HeightMapError::InvalidFormat(format!("bad PGM header value '{}'", token)))
+}
+
+impl HeightMap {
+    /// Loads a binary (`P5`) or ASCII (`P2`) PGM. Samples are mapped from `0..=maxval` onto
+    /// `-1.0..=1.0`, the same range `make_planet` produces.
+    pub fn from_pgm<R: Read>(reader: R) -> Result<HeightMap, HeightMapError> {
+        let data = read_all(reader)?;
+        let (header, data_start) = pgm_header(&data, 4)?;
+        let width = parse_pgm_number(&header[1])?;
+        let height = parse_pgm_number(&header[2])?;
+        let max_value = parse_pgm_number(&header[3])?;
+        if max_value == 0 || max_value > 65535 {
+            return Err(HeightMapError::InvalidFormat(format!("PGM maxval {} out of range", max_value)));
+        }
+        let size = check_size(width, height)?;
+        let heights = match header[0].as_str() {
+            "P5" => {
+                let bytes_per_sample = if max_value < 256 { 1 } else { 2 };
+                let data = data.get(data_start..).unwrap_or(&[]);
+                heights_from_samples(data, size, bytes_per_sample, max_value as u32, Endianness::Big)?
+            }
+            "P2" => {
+                let text = String::from_utf8_lossy(data.get(data_start..).unwrap_or(&[])).into_owned();
+                let heights: Vec<f64> = text
+                    .split_ascii_whitespace()
+                    .take(size * size)
+                    .map(|token| parse_pgm_number(token).map(|v| sample_to_height(v.min(max_value) as u32, max_value as u32)))
+                    .collect::<Result<_, _>>()?;
+                if heights.len() < size * size {
+                    return Err(HeightMapError::Truncated { expected: size * size, actual: heights.len() });
+                }
+                heights
+            }
+            magic => return Err(HeightMapError::InvalidFormat(format!("unsupported PGM magic '{}'", magic))),
+        };
+        Ok(HeightMap::from_grid(size, &heights, Some(ColorGradient::new().build_terrain_gradient())))
+    }
+
+    /// Loads an 8 or 16-bit grayscale PNG. Samples are mapped onto `-1.0..=1.0`.
+    pub fn from_png_gray16<R: Read>(reader: R) -> Result<HeightMap, HeightMapError> {
+        let mut decoder = png::Decoder::new(reader);
+        decoder.set_transformations(png::Transformations::IDENTITY);
+        let mut png_reader = decoder.read_info()?;
+        let info = png_reader.info();
+        if info.color_type != png::ColorType::Grayscale {
+            return Err(HeightMapError::InvalidFormat(format!("expected a grayscale PNG, got {:?}", info.color_type)));
+        }
+        let (bytes_per_sample, max_value) = match info.bit_depth {
+            png::BitDepth::Eight => (1, 255),
+            png::BitDepth::Sixteen => (2, 65535),
+            bit_depth => return Err(HeightMapError::UnsupportedBitDepth(bit_depth as u32)),
+        };
+        let size = check_size(info.width as usize, info.height as usize)?;
+        let mut buffer = vec![0; png_reader.output_buffer_size()];
+        let frame = png_reader.next_frame(&mut buffer)?;
+        let heights = heights_from_samples(&buffer[..frame.buffer_size()], size, bytes_per_sample, max_value, Endianness::Big)?;
+        Ok(HeightMap::from_grid(size, &heights, Some(ColorGradient::new().build_terrain_gradient())))
+    }
+
+    /// Loads headerless 16-bit samples in row-major order. Samples are mapped onto `-1.0..=1.0`.
+    pub fn from_raw_u16<R: Read>(reader: R, width: usize, height: usize, endianness: Endianness) -> Result<HeightMap, HeightMapError> {
+        let size = check_size(width, height)?;
+        let data = read_all(reader)?;
+        let heights = heights_from_samples(&data, size, 2, 65535, endianness)?;
+        Ok(HeightMap::from_grid(size, &heights, Some(ColorGradient::new().build_terrain_gradient())))
+    }
+
+    fn samples_u16(&self) -> Vec<u16> {
+        let size = self.size();
+        let mut samples = Vec::with_capacity(size * size);
+        for y in 0..size {
+            for x in 0..size {
+                samples.push(height_to_sample(self.read(self.num_levels()-1, x, y)));
+            }
+        }
+        samples
+    }
+
+    fn write_png<W: Write>(&self, writer: W, color_type: png::ColorType, bit_depth: png::BitDepth, data: &[u8]) -> Result<(), HeightMapError> {
+        let size = self.size() as u32;
+        let mut encoder = png::Encoder::new(writer, size, size);
+        encoder.set_color(color_type);
+        encoder.set_depth(bit_depth);
+        let mut png_writer = encoder.write_header()?;
+        png_writer.write_image_data(data)?;
+        png_writer.finish()?;
+        Ok(())
+    }
+
+    /// Writes the finest level as a 16-bit binary PGM that `from_pgm` reads back. Like every
+    /// 16-bit export, heights outside `-1.0..=1.0` are clamped.
+    pub fn save_pgm<W: Write>(&self, mut writer: W) -> Result<(), HeightMapError> {
+        let size = self.size();
+        write!(writer, "P5\n{} {}\n65535\n", size, size)?;
+        let data: Vec<u8> = self.samples_u16().iter().flat_map(|sample| sample.to_be_bytes()).collect();
+        writer.write_all(&data)?;
+        Ok(())
+    }
+
+    /// Writes the finest level as a 16-bit grayscale PNG that `from_png_gray16` reads back.
+    pub fn save_png16<W: Write>(&self, writer: W) -> Result<(), HeightMapError> {
+        let data: Vec<u8> = self.samples_u16().iter().flat_map(|sample| sample.to_be_bytes()).collect();
+        self.write_png(writer, png::ColorType::Grayscale, png::BitDepth::Sixteen, &data)
+    }
+
+    /// Writes the finest level as headerless 16-bit samples that `from_raw_u16` reads back.
+    pub fn save_raw<W: Write>(&self, mut writer: W, endianness: Endianness) -> Result<(), HeightMapError> {
+        let data: Vec<u8> = self.samples_u16()
+            .iter()
+            .flat_map(|sample| match endianness {
+                Endianness::Big => sample.to_be_bytes(),
+                Endianness::Little => sample.to_le_bytes(),
+            })
+            .collect();
+        writer.write_all(&data)?;
+        Ok(())
+    }
+
+    /// Writes the finest level as an RGBA PNG coloured with the map's colour gradient, for
+    /// inspection only; it cannot be loaded back as a height map.
+    pub fn save_color_png<W: Write>(&self, writer: W) -> Result<(), HeightMapError> {
+        let color_gradient = self.color_gradient().ok_or(HeightMapError::MissingColorGradient)?;
+        let size = self.size();
+        let mut data = Vec::with_capacity(size * size * 4);
+        for y in 0..size {
+            for x in 0..size {
+                data.extend_from_slice(&color_gradient.get_color(self.read(self.num_levels()-1, x, y)));
+            }
+        }
+        self.write_png(writer, png::ColorType::Rgba, png::BitDepth::Eight, &data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 4x4 grid of 16-bit samples covering the whole range.
+    fn samples() -> Vec<u16> {
+        (0..16).map(|i| (i * 65535 / 15) as u16).collect()
+    }
+
+    fn assert_heights(height_map: &HeightMap, samples: &[u16], max_value: u32) {
+        let size = height_map.size();
+        assert_eq!(size * size, samples.len());
+        for (i, &sample) in samples.iter().enumerate() {
+            let h = height_map.read(height_map.num_levels()-1, i % size, i / size);
+            assert_eq!(h, sample_to_height(sample as u32, max_value), "sample {}", i);
+        }
+    }
+
+    #[test]
+    fn loads_binary_pgm() {
+        let samples = samples();
+        let mut data = b"P5\n# comment\n4 4\n65535\n".to_vec();
+        data.extend(samples.iter().flat_map(|s| s.to_be_bytes()));
+        assert_heights(&HeightMap::from_pgm(&data[..]).unwrap(), &samples, 65535);
+
+        let bytes: Vec<u16> = (0..16).map(|i| i * 17).collect();
+        let mut data = b"P5 4 4 255\n".to_vec();
+        data.extend(bytes.iter().map(|&s| s as u8));
+        assert_heights(&HeightMap::from_pgm(&data[..]).unwrap(), &bytes, 255);
+    }
+
+    #[test]
+    fn loads_ascii_pgm() {
+        let samples = samples();
+        let mut text = "P2\n4 4\n65535\n".to_string();
+        for row in samples.chunks(4) {
+            let row: Vec<String> = row.iter().map(|s| s.to_string()).collect();
+            text += &row.join(" ");
+            text += "\n";
+        }
+        assert_heights(&HeightMap::from_pgm(text.as_bytes()).unwrap(), &samples, 65535);
+    }
+
+    #[test]
+    fn loads_png() {
+        let samples = samples();
+        let mut data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut data, 4, 4);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Sixteen);
+        let mut writer = encoder.write_header().unwrap();
+        let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_be_bytes()).collect();
+        writer.write_image_data(&bytes).unwrap();
+        writer.finish().unwrap();
+        assert_heights(&HeightMap::from_png_gray16(&data[..]).unwrap(), &samples, 65535);
+    }
+
+    #[test]
+    fn loads_raw_in_both_byte_orders() {
+        let samples = samples();
+        let little: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        let big: Vec<u8> = samples.iter().flat_map(|s| s.to_be_bytes()).collect();
+        assert_heights(&HeightMap::from_raw_u16(&little[..], 4, 4, Endianness::Little).unwrap(), &samples, 65535);
+        assert_heights(&HeightMap::from_raw_u16(&big[..], 4, 4, Endianness::Big).unwrap(), &samples, 65535);
+    }
+
+    #[test]
+    fn rejects_bad_sizes() {
+        let huge = b"P5\n4294967296 4294967296\n255\n";
+        assert!(matches!(HeightMap::from_pgm(&huge[..]), Err(HeightMapError::TooLarge { .. })));
+        let data = [0u8; 8];
+        assert!(matches!(HeightMap::from_raw_u16(&data[..], 1 << 20, 1 << 20, Endianness::Big), Err(HeightMapError::TooLarge { .. })));
+        assert!(matches!(HeightMap::from_raw_u16(&data[..], 4, 2, Endianness::Big), Err(HeightMapError::NotSquare { .. })));
+        assert!(matches!(HeightMap::from_raw_u16(&data[..], 3, 3, Endianness::Big), Err(HeightMapError::NotPowerOfTwo { .. })));
+        assert!(matches!(HeightMap::from_raw_u16(&data[..], 4, 4, Endianness::Big), Err(HeightMapError::Truncated { expected: 32, actual: 8 })));
+    }
+
+    fn assert_reloads(original: &HeightMap, reloaded: &HeightMap) {
+        let fine = original.num_levels()-1;
+        assert_eq!(original.size(), reloaded.size());
+        for z in 0..original.size() {
+            for x in 0..original.size() {
+                let (a, b) = (original.read(fine, x, z), reloaded.read(fine, x, z));
+                assert!((a - b).abs() <= 1.0 / 65535.0, "({}, {}): {} vs {}", x, z, a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn saved_maps_reload_within_one_step() {
+        let heights: Vec<f64> = (0..64).map(|i| (i as f64 * 0.73).sin()).collect();
+        let original = HeightMap::from_grid(8, &heights, None);
+
+        let mut pgm = Vec::new();
+        original.save_pgm(&mut pgm).unwrap();
+        assert_reloads(&original, &HeightMap::from_pgm(&pgm[..]).unwrap());
+
+        let mut png = Vec::new();
+        original.save_png16(&mut png).unwrap();
+        assert_reloads(&original, &HeightMap::from_png_gray16(&png[..]).unwrap());
+
+        for endianness in [Endianness::Little, Endianness::Big] {
+            let mut raw = Vec::new();
+            original.save_raw(&mut raw, endianness).unwrap();
+            assert_reloads(&original, &HeightMap::from_raw_u16(&raw[..], 8, 8, endianness).unwrap());
+        }
+    }
+}
@@ -0,0 +1,28 @@
+/// Summary of the finest-level heights under a node of the height pyramid, in stored units
+/// like `HeightMap::read`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct HeightStats {
+    /// Lowest height, e.g. for the sea floor or analytics.
+    pub min: f64,
+    /// Highest height, used to skip occluded nodes.
+    pub max: f64,
+    /// Mean height, used to draw far terrain at coarser levels.
+    pub mean: f64,
+}
+
+impl HeightStats {
+    /// Stats of a single cell.
+    pub fn new(height: f64) -> HeightStats {
+        HeightStats { min: height, max: height, mean: height }
+    }
+
+    /// Stats of a node from those of its four equally sized children.
+    pub fn merge(children: [HeightStats; 4]) -> HeightStats {
+        let [a, b, c, d] = children;
+        HeightStats {
+            min: a.min.min(b.min).min(c.min.min(d.min)),
+            max: a.max.max(b.max).max(c.max.max(d.max)),
+            mean: 0.25 * (a.mean + b.mean + c.mean + d.mean),
+        }
+    }
+}
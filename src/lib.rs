@@ -1,41 +1,75 @@
-use height_map::TimeHeight;
+use colour::{pack_rgb, scale_rgb};
+use sky::SkyShader;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use wasm_bindgen::prelude::*;
-use js_sys::Uint32Array;
 
 mod aabb;
+mod bindings;
+mod brush;
 mod acos;
 mod camera;
+mod colour;
 mod complexplanet;
+mod cos;
+mod edit_journal;
+mod fog;
+mod framebuffer;
 mod height_map;
+mod height_map_io;
+mod height_stats;
 mod vec2;
 mod vec3;
+mod lighting;
 mod max;
 mod min;
 mod one;
 mod quad_tree;
+mod pick;
 mod quaternion;
 mod ray2;
+mod render_options;
+mod shadow_map;
 mod sin;
+mod sky;
+mod sprite;
 mod sqrt;
 mod transform3;
+mod traversal;
+mod water;
 mod zero;
 
 pub use aabb::Aabb;
+pub use bindings::{BrushController, CameraController, PickResult, Renderer, Terrain};
 pub use acos::Acos;
+pub use brush::{Brush, BrushOp, BrushShape, Falloff};
 pub use camera::Camera;
-pub use complexplanet::make_planet;
-pub use height_map::HeightMap;
+pub use complexplanet::{make_planet, PlanetConfig};
+pub use cos::Cos;
+pub use fog::{AerialPerspective, FogConfig, FogMode};
+pub use framebuffer::Framebuffer;
+pub use height_map::{HeightFilter, HeightMap, BLOCK_SIZE, HEIGHT_SCALE, MAX_LEVELS};
+pub use height_map_io::{Endianness, HeightMapError};
+pub use height_stats::HeightStats;
 pub use vec2::Vec2;
 pub use vec3::Vec3;
+pub use lighting::LightingConfig;
 pub use max::Max;
 pub use min::Min;
 pub use one::One;
+pub use pick::{pick, PickHit};
 pub use quad_tree::QuadTree;
 pub use quaternion::Quaternion;
 pub use ray2::Ray2;
+pub use render_options::RenderOptions;
+pub use shadow_map::ShadowMap;
 pub use sin::Sin;
+pub use sky::{CloudConfig, SkyConfig};
+pub use sprite::{Sprite, SpriteImage, Sprites};
 pub use sqrt::Sqrt;
 pub use transform3::Transform3;
+pub use traversal::{DdaTraversal, LevelOfDetail, QuadTreeTraversal, RayTraversal, TimeHeight, Traversal};
+pub use water::{FoamConfig, WaterConfig};
 pub use zero::Zero;
 
 #[wasm_bindgen]
@@ -46,141 +80,323 @@ extern {
     pub fn log(s: &str);
 }
 
-use console_error_panic_hook;
+use std::ops::Range;
 use std::panic;
 
-fn init_panic_hook() {
+pub(crate) fn init_panic_hook() {
     panic::set_hook(Box::new(console_error_panic_hook::hook));
 }
 
-#[wasm_bindgen]
-pub fn main(screen: *mut Vec<u32>, height_map: *const HeightMap, angle: f64) {
-    init_panic_hook();
-    //
-    let screen = unsafe { &mut *screen };
-    let height_map = unsafe { &*height_map };
-    //
-    main2(
-        height_map,
-        |offset, colour| {
-            screen[offset] = colour;
-        },
-        angle,
-    );
+/// Vertical field of view of the renderer in degrees.
+const FOV_Y: f64 = 45.0;
+
+/// Rolls smaller than this (in radians) are rendered directly without the rotation pass.
+const MIN_ROLL: f64 = 1.0e-4;
+
+/// The camera path used by the original demo: orbiting the map centre at radius 4000 and
+/// height 500, looking level towards the centre. `angle` is in degrees.
+pub fn orbit_camera_space(angle: f64) -> Transform3<f64> {
+    let angle2 = angle.to_radians();
+    let cam_pos = Vec3::new(4000.0 * angle2.cos(), 500.0, -4000.0 * angle2.sin());
+    Transform3::new(
+        cam_pos,
+        Quaternion::from_yaw_pitch_roll(angle2 + 0.5 * std::f64::consts::PI, 0.0, 0.0),
+    )
 }
 
-#[wasm_bindgen]
-pub fn alloc_screen() -> *mut Vec<u32> {
-    let mut screen: Vec<u32> = Vec::with_capacity(64000);
-    for _i in 0..64000 {
-        screen.push(0);
+pub fn main2(height_map: &HeightMap, screen: &mut Framebuffer, camera_space: &Transform3<f64>, options: &RenderOptions) {
+    render_columns(height_map, screen, camera_space, options, 0..screen.width());
+}
+
+/// Renders only the screen columns in `columns`, leaving the rest of `screen` untouched.
+/// Columns do not depend on each other, so a frame rendered in strips, e.g. one per worker,
+/// is pixel-identical to one rendered by `main2`.
+pub fn render_columns(height_map: &HeightMap, screen: &mut Framebuffer, camera_space: &Transform3<f64>, options: &RenderOptions, columns: Range<usize>) {
+    let columns = columns.start.min(screen.width())..columns.end.min(screen.width());
+    if columns.is_empty() || screen.height() == 0 {
+        return;
+    }
+    let screen_width = screen.width() as f64;
+    let screen_height = screen.height() as f64;
+    let mut camera = Camera::new(*camera_space, screen_width, screen_height, FOV_Y);
+    camera.pixel_error = options.pixel_error;
+    let (_, _, roll) = camera.yaw_pitch_roll();
+    if roll.abs() < MIN_ROLL {
+        let upright = camera.upright(screen_width, screen_height);
+        render_upright(height_map, screen, &upright, options, columns);
+        return;
+    }
+    // Roll: render upright into a square buffer big enough to cover the screen at any
+    // rotation, then walk rotated scanlines through it. Only the buffer columns the strip
+    // samples from are rendered.
+    let side = rolled_buffer_side(screen.width(), screen.height());
+    let mut buffer = Framebuffer::new(side, side);
+    if screen.has_depth() {
+        buffer.enable_depth();
     }
-    Box::into_raw(Box::new(screen))
+    let upright = camera.upright(side as f64, side as f64);
+    let buffer_columns = rotated_footprint(&buffer, screen, roll, &columns);
+    render_upright(height_map, &mut buffer, &upright, options, buffer_columns);
+    blit_rotated(&buffer, screen, roll, background_colour(options), columns);
 }
 
-#[wasm_bindgen]
-pub fn free_screen(screen: *mut Vec<u32>) {
-    let _ = unsafe { Box::from_raw(screen) };
+/// Side of the square upright buffer rendered for a rolled camera: big enough to cover the
+/// screen at any rotation.
+fn rolled_buffer_side(screen_width: usize, screen_height: usize) -> usize {
+    (screen_width as f64).hypot(screen_height as f64).ceil() as usize + 2
 }
 
-#[wasm_bindgen]
-pub fn screen_get_ptr(screen: *mut Vec<u32>) -> *mut u32 {
-    let screen = unsafe { &mut *screen };
-    screen.as_mut_ptr()
+/// Range of `src` columns that `blit_rotated` reads for destination columns `columns`, with
+/// a little slack for its incremental stepping.
+fn rotated_footprint(src: &Framebuffer, dst: &Framebuffer, roll: f64, columns: &Range<usize>) -> Range<usize> {
+    let (sin, cos) = roll.sin_cos();
+    let src_cx = 0.5 * src.width() as f64;
+    let dst_cx = 0.5 * dst.width() as f64;
+    let dst_cy = 0.5 * dst.height() as f64;
+    let mut min_sx = f64::INFINITY;
+    let mut max_sx = f64::NEG_INFINITY;
+    for x in [columns.start as f64, columns.end as f64] {
+        for y in [0.0, dst.height() as f64] {
+            let sx = src_cx + (x - dst_cx) * cos + (y - dst_cy) * sin;
+            min_sx = min_sx.min(sx);
+            max_sx = max_sx.max(sx);
+        }
+    }
+    let start = (min_sx.floor() - 2.0).max(0.0) as usize;
+    let end = ((max_sx.ceil() + 2.0).max(0.0) as usize).min(src.width());
+    start.min(end)..end
 }
 
-#[wasm_bindgen]
-pub fn create_height_map() -> *mut HeightMap {
-    Box::into_raw(Box::new(HeightMap::new(8)))
+/// Fills everything the terrain and sky do not cover, so fogged terrain fades into it.
+fn background_colour(options: &RenderOptions) -> u32 {
+    if let Some(sky) = &options.sky {
+        return pack_rgb(sky.horizon_colour);
+    }
+    match &options.fog {
+        Some(fog) => pack_rgb(fog.colour),
+        None => 0xFF000000,
+    }
 }
 
-#[wasm_bindgen]
-pub fn free_height_map(height_map: *mut HeightMap) {
-    let _ = unsafe { Box::from_raw(height_map) };
+fn render_upright(height_map: &HeightMap, screen: &mut Framebuffer, camera: &Camera<f64>, options: &RenderOptions, columns: Range<usize>) {
+    let height = screen.height();
+    let sky_shader = options.sky.as_ref().map(|sky| {
+        SkyShader::new(sky, options.lighting.as_ref().map(|lighting| lighting.sun_direction), camera.space.origin)
+    });
+    // Columns are independent, so render each into its own contiguous slice of a column-major
+    // buffer and transpose at the end.
+    let x0 = columns.start;
+    let mut buffer = vec![background_colour(options); columns.len() * height];
+    let mut depth = vec![f32::INFINITY; columns.len() * height];
+    let render = |(i, (column, column_depth)): (usize, (&mut [u32], &mut [f32]))| {
+        render_column(height_map, camera, options, sky_shader.as_ref(), x0 + i, column, column_depth)
+    };
+    #[cfg(feature = "parallel")]
+    buffer.par_chunks_mut(height).zip(depth.par_chunks_mut(height)).enumerate().for_each(render);
+    #[cfg(not(feature = "parallel"))]
+    buffer.chunks_mut(height).zip(depth.chunks_mut(height)).enumerate().for_each(render);
+    options.sprites.draw(camera, options.fog.as_ref(), columns, height, &mut buffer, &mut depth);
+    for (i, (column, column_depth)) in buffer.chunks(height).zip(depth.chunks(height)).enumerate() {
+        for y in 0..height {
+            screen.set_pixel(x0 + i, y, column[y]);
+            screen.set_depth(x0 + i, y, column_depth[y]);
+        }
+    }
 }
 
-pub fn main2<WriteScreen: FnMut(usize,u32)>(height_map: &HeightMap, mut write_screen: WriteScreen, angle: f64) {
-    //
-    //let height_map = HeightMap::new(8);
-    if false {
-        for level in 0..8 {
-            let width = 1 << level;
-            let mut offset: usize = width;
-            for y in 0..width {
-                for x in 0..width {
-                    let height = height_map.read(level, x, y);
-                    let c = ((height as i32).abs() as u32) & 0xFF;
-                    write_screen(offset + x, 0xFF808000 | c);
+/// Renders screen column `x` top to bottom into `column`, which starts out filled with the
+/// background colour, and the distance to each span into `depth`, which starts out infinite.
+#[allow(clippy::too_many_arguments)]
+fn render_column(height_map: &HeightMap, camera: &Camera<f64>, options: &RenderOptions, sky_shader: Option<&SkyShader>, x: usize, column: &mut [u32], depth: &mut [f32]) {
+    let screen_width = camera.screen_width;
+    let screen_height = camera.screen_height;
+    let ray_xz = camera.screen_x_to_ray_xz(x as f64);
+    if ray_xz.is_none() {
+        return;
+    }
+    let ray_xz = ray_xz.unwrap();
+    let mut y_max = screen_height as i32;
+    let visit = |TimeHeight { t, t_exit, height, raw_height, cell_x, cell_z, level }: TimeHeight, early_bail_test: bool, color_op: Option<[u8; 4]>| -> bool {
+        let pt = ray_xz.position_from_time(t);
+        let mut height = height;
+        // Depth of water over this cell, if it is under the sea.
+        let mut water_depth = None;
+        if early_bail_test {
+            if let Some(water) = &options.water {
+                height = height.max(water.sea_level);
+            }
+        } else {
+            (height, water_depth) = surface_height(height_map, options, pt, height, raw_height, level);
+        }
+        if early_bail_test {
+            // Filtered heights near a node's edge blend in its neighbours and can rise
+            // above the node's max, so nothing can be skipped safely.
+            if options.height_filter.is_some() {
+                return false;
+            }
+            // At a fixed height the projection moves monotonically with distance, so
+            // the node's top edge on screen is at its entry or its exit.
+            let pt_exit = ray_xz.position_from_time(t_exit);
+            let y_entry = camera.project_y(Vec3::new(pt.x, height, pt.y));
+            let y_exit = camera.project_y(Vec3::new(pt_exit.x, height, pt_exit.y));
+            let yi = (y_entry.min(y_exit) as i32).clamp(0, column.len() as i32 - 1);
+            return yi >= y_max;
+        }
+        let y1 = camera.project_y(Vec3::new(pt.x, height, pt.y));
+        let yi = (y1 as i32).clamp(0, column.len() as i32 - 1);
+        if yi < y_max {
+            let color2: u32;
+            if let Some(color) = color_op {
+                let mut rgb = [color[0], color[1], color[2]];
+                if let Some(lighting) = &options.lighting {
+                    rgb = scale_rgb(rgb, lighting.intensity(height_map.normal(cell_x, cell_z)));
+                }
+                if let (Some(water), Some(depth)) = (&options.water, water_depth) {
+                    let eye_height = camera.space.origin.y - height;
+                    let dir = Vec3::new(ray_xz.direction.x * t, -eye_height, ray_xz.direction.y * t).normalize();
+                    let reflection = match sky_shader {
+                        Some(sky_shader) => sky_shader.rgb(Vec3::new(dir.x, -dir.y, dir.z)),
+                        None => water.reflection_colour,
+                    };
+                    rgb = water.shade(rgb, depth, -dir.y, reflection);
+                }
+                if let Some(shadow_map) = options.shadow_map.as_ref().filter(|shadow_map| shadow_map.fits(height_map)) {
+                    rgb = scale_rgb(rgb, shadow_map.light(cell_x, cell_z) as f64);
+                }
+                if let Some(fog) = &options.fog {
+                    rgb = fog.apply(rgb, t, height);
                 }
-                offset += 320;
+                color2 = pack_rgb(rgb);
+            } else {
+                let c = (height as i32).unsigned_abs() & 0xFF;
+                color2 = 0xFF808000 | c;
             }
+            let distance = t.hypot(height - camera.space.origin.y) as f32;
+            for y in yi..y_max {
+                column[y as usize] = color2;
+                depth[y as usize] = distance;
+            }
+            y_max = yi;
+        }
+        false
+    };
+    options.traversal.walk_lod(height_map, ray_xz, camera.level_of_detail(), visit);
+    if let Some(sky_shader) = sky_shader {
+        // Everything above the highest terrain span in this column is sky.
+        for y in 0..y_max {
+            let dir_camera = Vec3::new(
+                x as f64 - 0.5 * screen_width,
+                0.5 * screen_height + camera.shear_y - y as f64,
+                -camera.screen_dist,
+            );
+            let dir = camera.space.vector_from_space(dir_camera).normalize();
+            column[y as usize] = sky_shader.colour(dir);
         }
-        return;
     }
-    //let aabb = Aabb::new(-128.0, -100.0, -128.0, 128.0, -80.0, 128.0);
-    let screen_width = 320.0;
-    let screen_height = 200.0;
-    let fov_y: f64 = 45.0;
-    let screen_dist = 0.5 * screen_height / (0.5 * fov_y).to_radians().tan();
-    let angle2 = angle.to_radians();
-    let q = Quaternion {
-        w: (0.5 * angle2).cos(),
-        x: 0.0,
-        y: (0.5 * angle2).sin(),
-        z: 0.0,
+}
+
+/// Height drawn for a cell or level-of-detail leaf at `level` hit at `pt`: finest cells are
+/// resampled by the height filter if there is one, and anything under the sea is raised to
+/// the sea level, along with the depth of that water. With water on, land is drawn at its
+/// true height, so a sea level below zero shows the ground between it and zero.
+fn surface_height(height_map: &HeightMap, options: &RenderOptions, pt: Vec2<f64>, height: f64, raw_height: f64, level: usize) -> (f64, Option<f64>) {
+    let filter = options.height_filter.filter(|_| level == height_map.num_levels()-1);
+    let Some(water) = &options.water else {
+        return (filter.map_or(height, |filter| height_map.sample(pt.x, pt.y, filter)), None);
     };
-    let cam_pos = q.rotate(Vec3::new(4000.0, 0.0, 0.0));
-    let w = cam_pos.normalize();
-    let up = Vec3::new(0.0, 1.0, 0.0);
-    let u = up.cross(w).normalize();
-    let camera = Camera {
-        space: Transform3::new(
-            cam_pos + Vec3::new(0.0, 500.0, 0.0),
-            Quaternion::from_wu(w, u),
-        ),
-        screen_width,
-        screen_height,
-        screen_dist
+    if raw_height < water.sea_level {
+        return (water.sea_level, Some(water.sea_level - raw_height));
+    }
+    let height = match filter {
+        Some(filter) => (height_map.sample_raw(pt.x, pt.y, filter) * HEIGHT_SCALE).max(water.sea_level),
+        None => raw_height,
     };
-    for i in 0..64000 {
-        write_screen(i, 0xFF000000);
+    (height, None)
+}
+
+/// Copies `src` into `dst` rotated by `roll` radians about both centres. Each destination
+/// scanline becomes a straight line through `src`, stepped incrementally. Only destination
+/// `columns` are written, but stepping always starts from the left edge so strips match.
+fn blit_rotated(src: &Framebuffer, dst: &mut Framebuffer, roll: f64, background: u32, columns: Range<usize>) {
+    let (sin, cos) = roll.sin_cos();
+    let src_cx = 0.5 * src.width() as f64;
+    let src_cy = 0.5 * src.height() as f64;
+    let dst_cx = 0.5 * dst.width() as f64;
+    let dst_cy = 0.5 * dst.height() as f64;
+    for y in 0..dst.height() {
+        let dx = 0.5 - dst_cx;
+        let dy = y as f64 + 0.5 - dst_cy;
+        let mut sx = src_cx + dx * cos + dy * sin;
+        let mut sy = src_cy - dx * sin + dy * cos;
+        for x in 0..columns.end {
+            if x < columns.start {
+                sx += cos;
+                sy -= sin;
+                continue;
+            }
+            let (colour, distance) = if sx >= 0.0 && sy >= 0.0 && (sx as usize) < src.width() && (sy as usize) < src.height() {
+                (src.get_pixel(sx as usize, sy as usize), src.get_depth(sx as usize, sy as usize))
+            } else {
+                (background, f32::INFINITY)
+            };
+            dst.set_pixel(x, y, colour);
+            dst.set_depth(x, y, distance);
+            sx += cos;
+            sy -= sin;
+        }
     }
-    for x in 0..screen_width as u32 {
-        let ray_xz = camera.screen_x_to_ray_xz(x as f64);
-        if ray_xz.is_none() {
-            continue;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> RenderOptions {
+        RenderOptions {
+            lighting: Some(LightingConfig::default()),
+            water: Some(WaterConfig { sea_level: 50.0, ..WaterConfig::default() }),
+            fog: Some(FogConfig {
+                mode: FogMode::Exponential { density: 0.0002 },
+                colour: [180, 190, 200],
+                height_falloff: 0.0,
+                aerial_perspective: None,
+            }),
+            ..RenderOptions::default()
         }
-        let ray_xz = ray_xz.unwrap();
-        let mut y_max = screen_height as i32;
-        height_map.ray_xz_intersection_2pt5d(
-            ray_xz,
-            |TimeHeight { t, height }, early_bail_test, color_op| {
-                let pt = ray_xz.position_from_time(t);
-                let y1 = camera.project_y(Vec3::new(pt.x, height, pt.y));
-                let yi = (y1 as i32).max(0).min(199);
-                if early_bail_test {
-                    return yi > y_max;
-                }
-                if yi < y_max {
-                    for y in yi..y_max {
-                        let y = y as u32;
-                        let offset = (y << 8) + (y << 6) + x;
-                        let color2: u32;
-                        if let Some(color) = color_op {
-                            color2 = 0xFF000000 | ((color[2] as u32) << 16) | ((color[1] as u32) << 8) | (color[0] as u32);
-                        } else {
-                            let c = ((height as i32).abs() as u32) & 0xFF;
-                            color2 = 0xFF808000 | c;
-                        }
-                        write_screen(offset as usize, color2);
-                    }
-                    y_max = yi;
+    }
+
+    fn render(height_map: &HeightMap, camera_space: &Transform3<f64>, options: &RenderOptions, strip_width: Option<usize>) -> Framebuffer {
+        let mut screen = Framebuffer::new(96, 60);
+        screen.enable_depth();
+        match strip_width {
+            Some(strip_width) => {
+                for x0 in (0..screen.width()).step_by(strip_width) {
+                    render_columns(height_map, &mut screen, camera_space, options, x0..x0 + strip_width);
                 }
-                return false;
             }
-        );
+            None => main2(height_map, &mut screen, camera_space, options),
+        }
+        screen
+    }
 
-        // TODO
+    /// A frame rendered whole, which runs its columns on rayon with the `parallel` feature, or
+    /// in strips of any width matches one rendered a single column at a time.
+    #[test]
+    fn whole_and_strip_renders_match_serial() {
+        let height_map = HeightMap::new(6);
+        let options = options();
+        for roll in [0.0, 0.4] {
+            let camera_space = Transform3::new(
+                Vec3::new(-300.0, 900.0, 1200.0),
+                Quaternion::from_yaw_pitch_roll(0.3, -0.2, roll),
+            );
+            let serial = render(&height_map, &camera_space, &options, Some(1));
+            assert!(serial.depth().unwrap().iter().any(|d| d.is_finite()), "no terrain in view with roll {}", roll);
+            for strip_width in [None, Some(7), Some(32)] {
+                let screen = render(&height_map, &camera_space, &options, strip_width);
+                assert_eq!(screen.pixels(), serial.pixels(), "pixels differ with roll {} and strips {:?}", roll, strip_width);
+                assert_eq!(screen.depth(), serial.depth(), "depth differs with roll {} and strips {:?}", roll, strip_width);
+            }
+        }
     }
-    // TODO    
 }
@@ -0,0 +1,25 @@
+use crate::Vec3;
+
+pub struct LightingConfig {
+    /// Unit vector pointing from the terrain towards the sun.
+    pub sun_direction: Vec3<f64>,
+    /// Light reaching faces turned away from the sun, in `0.0..=1.0`.
+    pub ambient: f64,
+}
+
+impl LightingConfig {
+    /// Lambert term plus ambient for a unit surface normal.
+    pub fn intensity(&self, normal: Vec3<f64>) -> f64 {
+        let diffuse = normal.dot(self.sun_direction).max(0.0);
+        self.ambient + (1.0 - self.ambient) * diffuse
+    }
+}
+
+impl Default for LightingConfig {
+    fn default() -> Self {
+        LightingConfig {
+            sun_direction: Vec3::new(-0.5, 0.6, -0.4).normalize(),
+            ambient: 0.35,
+        }
+    }
+}
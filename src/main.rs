@@ -1,13 +1,318 @@
-use height_map_test::{HeightMap, main2};
-
-pub fn main() {
-    let height_map = HeightMap::new(8);
-    let mut screen: [u32; 64000] = [0; 64000];
-    main2(
-        &height_map,
-        |offset, colour| {
-            screen[offset] = colour;
-        },
-        0.0,
-    );
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::process::ExitCode;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use height_map_test::{
+    BLOCK_SIZE, CloudConfig, FogConfig, FogMode, Framebuffer, HeightFilter, HeightMap, LightingConfig, MAX_LEVELS, Quaternion, RenderOptions,
+    ShadowMap, SkyConfig, Sprite, SpriteImage, Sprites, Transform3, Traversal, Vec3, WaterConfig, main2, orbit_camera_space,
+};
+
+/// Largest accepted `--width` or `--height`, in pixels.
+const MAX_SCREEN_SIDE: usize = 16384;
+
+const USAGE: &str = "\
+usage: height-map-test render [options] --out FILE
+
+Renders a single frame without a browser and writes it to FILE (.png or .ppm).
+
+options:
+  --width N          framebuffer width in pixels, 1 to 16384 (default 320)
+  --height N         framebuffer height in pixels, 1 to 16384 (default 200)
+  --angle DEG        orbit the map centre like the browser demo (default 0)
+  --pos X,Y,Z        place the camera explicitly instead of orbiting
+  --yaw DEG          heading when --pos is given (default 0)
+  --pitch DEG        pitch when --pos is given (default 0)
+  --roll DEG         roll when --pos is given (default 0)
+  --filter NAME      smooth terrain heights: nearest, bilinear or bicubic
+  --fog SPEC         distance fog, exp:DENSITY or linear:START:END
+  --fog-colour R,G,B fog colour (default 170,190,210)
+  --sun X,Y,Z        shade terrain with a sun in this direction
+  --ambient A        light on faces turned away from the sun (default 0.35)
+  --shadows LIGHT    cast shadows from --sun, darkening shadowed cells to LIGHT
+  --sky KIND         draw a sky instead of black: clear or cloudy
+  --water LEVEL      draw water over terrain below this world-space height
+  --traversal NAME   ray walk: dda (default), flat or quadtree
+  --trees N          scatter N tree sprites over the land
+  --pixel-error PX   walk far terrain at coarser levels once cells are PX pixels wide
+  --levels N         quadtree levels of the generated map, 1 to 16 (default 8)
+  --height-map FILE  load terrain from a .pgm or grayscale .png instead
+  --out FILE         output image, .png or .ppm";
+
+struct RenderArgs {
+    width: usize,
+    height: usize,
+    angle: f64,
+    pos: Option<Vec3<f64>>,
+    yaw: f64,
+    pitch: f64,
+    roll: f64,
+    filter: Option<HeightFilter>,
+    fog: Option<FogMode>,
+    fog_colour: [u8; 3],
+    sun: Option<Vec3<f64>>,
+    ambient: f64,
+    shadow_light: Option<f32>,
+    sky: Option<SkyConfig>,
+    sea_level: Option<f64>,
+    traversal: Traversal,
+    trees: usize,
+    pixel_error: f64,
+    levels: usize,
+    height_map: Option<String>,
+    out: String,
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value '{}' for {}", value, flag))
+}
+
+fn parse_triple<T: std::str::FromStr + Copy>(flag: &str, value: &str) -> Result<[T; 3], String> {
+    let parts = value
+        .split(',')
+        .map(|part| parse_number::<T>(flag, part))
+        .collect::<Result<Vec<_>, _>>()?;
+    if parts.len() != 3 {
+        return Err(format!("{} expects three comma-separated values, got '{}'", flag, value));
+    }
+    Ok([parts[0], parts[1], parts[2]])
+}
+
+fn parse_render_args(args: &[String]) -> Result<RenderArgs, String> {
+    let mut r = RenderArgs {
+        width: 320,
+        height: 200,
+        angle: 0.0,
+        pos: None,
+        yaw: 0.0,
+        pitch: 0.0,
+        roll: 0.0,
+        filter: None,
+        fog: None,
+        fog_colour: [170, 190, 210],
+        sun: None,
+        ambient: LightingConfig::default().ambient,
+        shadow_light: None,
+        sky: None,
+        sea_level: None,
+        traversal: Traversal::Dda,
+        trees: 0,
+        pixel_error: 0.0,
+        levels: 8,
+        height_map: None,
+        out: String::new(),
+    };
+    let mut out = None;
+    let mut iter = args.iter();
+    while let Some(flag) = iter.next() {
+        let value = iter.next().ok_or_else(|| format!("missing value for {}", flag))?;
+        match flag.as_str() {
+            "--width" => r.width = parse_number(flag, value)?,
+            "--height" => r.height = parse_number(flag, value)?,
+            "--angle" => r.angle = parse_number(flag, value)?,
+            "--pos" => {
+                let [x, y, z] = parse_triple(flag, value)?;
+                r.pos = Some(Vec3::new(x, y, z));
+            }
+            "--yaw" => r.yaw = parse_number(flag, value)?,
+            "--pitch" => r.pitch = parse_number(flag, value)?,
+            "--roll" => r.roll = parse_number(flag, value)?,
+            "--filter" => {
+                r.filter = Some(match value.as_str() {
+                    "nearest" => HeightFilter::Nearest,
+                    "bilinear" => HeightFilter::Bilinear,
+                    "bicubic" => HeightFilter::Bicubic,
+                    _ => return Err(format!("unknown filter '{}'", value)),
+                });
+            }
+            "--fog" => {
+                let parts: Vec<&str> = value.split(':').collect();
+                r.fog = Some(match parts.as_slice() {
+                    ["exp", density] => FogMode::Exponential { density: parse_number(flag, density)? },
+                    ["linear", start, end] => FogMode::Linear {
+                        start: parse_number(flag, start)?,
+                        end: parse_number(flag, end)?,
+                    },
+                    _ => return Err(format!("--fog expects exp:DENSITY or linear:START:END, got '{}'", value)),
+                });
+            }
+            "--fog-colour" => r.fog_colour = parse_triple(flag, value)?,
+            "--sun" => {
+                let [x, y, z] = parse_triple(flag, value)?;
+                r.sun = Some(Vec3::new(x, y, z).normalize());
+            }
+            "--ambient" => r.ambient = parse_number(flag, value)?,
+            "--shadows" => r.shadow_light = Some(parse_number(flag, value)?),
+            "--sky" => {
+                r.sky = Some(match value.as_str() {
+                    "clear" => SkyConfig::default(),
+                    "cloudy" => SkyConfig {
+                        clouds: Some(CloudConfig::default()),
+                        ..SkyConfig::default()
+                    },
+                    _ => return Err(format!("unknown sky '{}'", value)),
+                });
+            }
+            "--water" => r.sea_level = Some(parse_number(flag, value)?),
+            "--traversal" => {
+                r.traversal = match value.as_str() {
+                    "dda" => Traversal::Dda,
+                    "flat" => Traversal::FlatDda,
+                    "quadtree" => Traversal::QuadTree,
+                    _ => return Err(format!("unknown traversal '{}'", value)),
+                };
+            }
+            "--trees" => r.trees = parse_number(flag, value)?,
+            "--pixel-error" => r.pixel_error = parse_number(flag, value)?,
+            "--levels" => r.levels = parse_number(flag, value)?,
+            "--height-map" => r.height_map = Some(value.clone()),
+            "--out" => out = Some(value.clone()),
+            _ => return Err(format!("unknown option {}", flag)),
+        }
+    }
+    r.out = out.ok_or("--out is required")?;
+    if r.width == 0 || r.height == 0 || r.width > MAX_SCREEN_SIDE || r.height > MAX_SCREEN_SIDE {
+        return Err(format!("--width and --height must be between 1 and {}", MAX_SCREEN_SIDE));
+    }
+    if r.shadow_light.is_some() && r.sun.is_none() {
+        return Err("--shadows needs --sun".to_string());
+    }
+    if r.levels == 0 || r.levels > MAX_LEVELS {
+        return Err(format!("--levels must be between 1 and {}", MAX_LEVELS));
+    }
+    Ok(r)
+}
+
+fn extension(path: &str) -> String {
+    Path::new(path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default()
+}
+
+fn load_height_map(path: &str) -> Result<HeightMap, String> {
+    let file = File::open(path).map_err(|err| format!("{}: {}", path, err))?;
+    let result = match extension(path).as_str() {
+        "pgm" => HeightMap::from_pgm(file),
+        "png" => HeightMap::from_png_gray16(file),
+        _ => return Err(format!("{}: height maps must be .pgm or .png", path)),
+    };
+    result.map_err(|err| format!("{}: {}", path, err))
+}
+
+/// A small conifer: a brown trunk under a stack of green triangles.
+fn tree_image() -> SpriteImage {
+    const WIDTH: usize = 16;
+    const HEIGHT: usize = 32;
+    let mut rgba = vec![0u8; WIDTH * HEIGHT * 4];
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            let dx = (x as f64 + 0.5 - 0.5 * WIDTH as f64).abs();
+            let colour = if y >= HEIGHT - 6 {
+                (dx < 1.5).then_some([90, 60, 30])
+            } else {
+                // Three tiers, each widening towards its base.
+                let tier = (y % 9) as f64 / 9.0;
+                let half_width = (0.3 + 0.7 * tier) * (2.0 + 6.0 * y as f64 / (HEIGHT - 6) as f64);
+                (dx < half_width).then_some(if x < WIDTH / 2 { [40, 110, 50] } else { [25, 80, 35] })
+            };
+            if let Some([r, g, b]) = colour {
+                rgba[(y * WIDTH + x) * 4..][..4].copy_from_slice(&[r, g, b, 255]);
+            }
+        }
+    }
+    SpriteImage::from_rgba(WIDTH, HEIGHT, &rgba)
+}
+
+/// Scatters `count` trees over cells above `sea_level`, the same ones on every run.
+fn scatter_trees(height_map: &HeightMap, count: usize, sea_level: Option<f64>) -> Sprites {
+    let mut sprites = Sprites::default();
+    let image = sprites.add_image(tree_image());
+    let mut rng = StdRng::seed_from_u64(1);
+    let half_size2 = 0.5 * height_map.size() as f64 * BLOCK_SIZE;
+    let mut attempts = 0;
+    while sprites.sprites.len() < count && attempts < count * 20 {
+        attempts += 1;
+        let x = rng.gen_range(-half_size2..half_size2);
+        let z = rng.gen_range(-half_size2..half_size2);
+        let y = height_map.sample(x, z, HeightFilter::Nearest);
+        if sea_level.is_some_and(|sea_level| y <= sea_level) {
+            continue;
+        }
+        sprites.add(Sprite {
+            position: Vec3::new(x, y, z),
+            size: 3.0 * BLOCK_SIZE,
+            image,
+        });
+    }
+    sprites
+}
+
+fn render(mut args: RenderArgs) -> Result<(), String> {
+    let out_format = extension(&args.out);
+    if out_format != "png" && out_format != "ppm" {
+        return Err(format!("{}: output must be .png or .ppm", args.out));
+    }
+    let height_map = match &args.height_map {
+        Some(path) => load_height_map(path)?,
+        None => HeightMap::new(args.levels),
+    };
+    let camera_space = match args.pos {
+        Some(pos) => Transform3::new(
+            pos,
+            Quaternion::from_yaw_pitch_roll(args.yaw.to_radians(), args.pitch.to_radians(), args.roll.to_radians()),
+        ),
+        None => orbit_camera_space(args.angle),
+    };
+    let options = RenderOptions {
+        height_filter: args.filter,
+        fog: args.fog.map(|mode| FogConfig {
+            mode,
+            colour: args.fog_colour,
+            height_falloff: 0.0,
+            aerial_perspective: None,
+        }),
+        lighting: args.sun.map(|sun_direction| LightingConfig {
+            sun_direction,
+            ambient: args.ambient,
+        }),
+        shadow_map: args.sun.zip(args.shadow_light).map(|(sun_direction, shadow_light)| {
+            ShadowMap::new(&height_map, sun_direction, shadow_light)
+        }),
+        sky: args.sky.take(),
+        water: args.sea_level.map(|sea_level| WaterConfig {
+            sea_level,
+            ..WaterConfig::default()
+        }),
+        traversal: args.traversal,
+        pixel_error: args.pixel_error,
+        sprites: scatter_trees(&height_map, args.trees, args.sea_level),
+    };
+    let mut screen = Framebuffer::new(args.width, args.height);
+    main2(&height_map, &mut screen, &camera_space, &options);
+    let file = File::create(&args.out).map_err(|err| format!("{}: {}", args.out, err))?;
+    let writer = BufWriter::new(file);
+    let result = if out_format == "png" {
+        screen.write_png(writer)
+    } else {
+        screen.write_ppm(writer)
+    };
+    result.map_err(|err| format!("{}: {}", args.out, err))
+}
+
+pub fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) != Some("render") {
+        eprintln!("{}", USAGE);
+        return ExitCode::from(2);
+    }
+    let result = parse_render_args(&args[1..]).and_then(render);
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
 }
@@ -0,0 +1,107 @@
+use crate::{rolled_buffer_side, surface_height, Camera, HeightMap, RayTraversal, RenderOptions, TimeHeight, Transform3, Vec3, FOV_Y, MIN_ROLL};
+
+/// The terrain under a screen pixel.
+pub struct PickHit {
+    /// Where the view ray through the pixel centre meets the drawn surface: the top or front
+    /// face of the cell, or the water over it.
+    pub world_pos: Vec3<f64>,
+    /// Finest-level cell, as passed to `HeightMap::read`.
+    pub cell: (usize, usize),
+    /// World-space height of the cell's terrain; the sea floor when it is under water.
+    pub height: f64,
+    /// Distance from the camera to `world_pos`.
+    pub distance: f64,
+}
+
+/// Finds the terrain cell `main2` draws at pixel (`x`, `y`) of a `screen_width` by
+/// `screen_height` frame with the same camera and options. Returns None for sky, background
+/// and pixels off the screen. Sprites are not picked.
+#[allow(clippy::too_many_arguments)]
+pub fn pick(height_map: &HeightMap, screen_width: usize, screen_height: usize, camera_space: &Transform3<f64>, options: &RenderOptions, x: usize, y: usize) -> Option<PickHit> {
+    if x >= screen_width || y >= screen_height {
+        return None;
+    }
+    let width = screen_width as f64;
+    let height = screen_height as f64;
+    let mut camera = Camera::new(*camera_space, width, height, FOV_Y);
+    camera.pixel_error = options.pixel_error;
+    let (_, _, roll) = camera.yaw_pitch_roll();
+    if roll.abs() < MIN_ROLL {
+        return pick_upright(height_map, &camera.upright(width, height), options, x, y);
+    }
+    // Find the upright buffer pixel `blit_rotated` copies here, stepping along the scanline
+    // the same way so the rounding matches.
+    let side = rolled_buffer_side(screen_width, screen_height) as f64;
+    let (sin, cos) = roll.sin_cos();
+    let dx = 0.5 - 0.5 * width;
+    let dy = y as f64 + 0.5 - 0.5 * height;
+    let mut sx = 0.5 * side + dx * cos + dy * sin;
+    let mut sy = 0.5 * side - dx * sin + dy * cos;
+    for _ in 0..x {
+        sx += cos;
+        sy -= sin;
+    }
+    if sx < 0.0 || sy < 0.0 || sx >= side || sy >= side {
+        return None;
+    }
+    pick_upright(height_map, &camera.upright(side, side), options, sx as usize, sy as usize)
+}
+
+/// Walks column `x` like `render_column` and returns the first cell whose span covers row `y`.
+fn pick_upright(height_map: &HeightMap, camera: &Camera<f64>, options: &RenderOptions, x: usize, y: usize) -> Option<PickHit> {
+    let ray_xz = camera.screen_x_to_ray_xz(x as f64)?;
+    let last_row = camera.screen_height as i32 - 1;
+    let row = y as i32;
+    let mut hit = None;
+    let visit = |TimeHeight { t, t_exit, height, raw_height, cell_x, cell_z, level }: TimeHeight, early_bail_test: bool, _: Option<[u8; 4]>| -> bool {
+        if hit.is_some() {
+            return true;
+        }
+        let pt = ray_xz.position_from_time(t);
+        if early_bail_test {
+            if options.height_filter.is_some() {
+                return false;
+            }
+            let height = match &options.water {
+                Some(water) => height.max(water.sea_level),
+                None => height,
+            };
+            // A node whose top edge is below the row cannot draw it, nor change which cell does.
+            let pt_exit = ray_xz.position_from_time(t_exit);
+            let y_entry = camera.project_y(Vec3::new(pt.x, height, pt.y));
+            let y_exit = camera.project_y(Vec3::new(pt_exit.x, height, pt_exit.y));
+            // A node the camera stands in projects its entry to infinity of either sign.
+            if !y_entry.is_finite() {
+                return false;
+            }
+            return (y_entry.min(y_exit) as i32).clamp(0, last_row) > row;
+        }
+        let (surface, water_depth) = surface_height(height_map, options, pt, height, raw_height, level);
+        let yi = (camera.project_y(Vec3::new(pt.x, surface, pt.y)) as i32).clamp(0, last_row);
+        if yi > row {
+            return false;
+        }
+        // Follow the view ray through the pixel centre down onto the cell's top face, or stop
+        // at its front face when it hits that first.
+        let dir_camera = Vec3::new(
+            x as f64 - 0.5 * camera.screen_width,
+            0.5 * camera.screen_height + camera.shear_y - (y as f64 + 0.5),
+            -camera.screen_dist,
+        );
+        let dir = camera.space.vector_from_space(dir_camera);
+        let slope = dir.y / dir.x.hypot(dir.z);
+        let origin = camera.space.origin;
+        let s = if slope < 0.0 { ((surface - origin.y) / slope).clamp(t, t_exit) } else { t };
+        let pt_hit = ray_xz.position_from_time(s);
+        let world_pos = Vec3::new(pt_hit.x, (origin.y + slope * s).min(surface), pt_hit.y);
+        hit = Some(PickHit {
+            world_pos,
+            cell: (cell_x, cell_z),
+            height: if water_depth.is_some() { raw_height } else { surface },
+            distance: (world_pos - origin).length(),
+        });
+        true
+    };
+    options.traversal.walk_lod(height_map, ray_xz, camera.level_of_detail(), visit);
+    hit
+}
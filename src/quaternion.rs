@@ -1,4 +1,4 @@
-use crate::{Acos, One, Sin, Sqrt, Vec3, Zero};
+use crate::{Acos, Cos, One, Sin, Sqrt, Vec3, Zero};
 use std::ops::{Add, Sub, Mul, Div, Neg};
 
 pub struct Quaternion<T> {
@@ -21,6 +21,32 @@ impl<T: Copy> Quaternion<T> {
         }
     }
 
+    pub fn from_axis_angle(axis: Vec3<T>, angle: T) -> Quaternion<T>
+    where
+        T: Mul<Output=T> + Div<Output=T> + Add<Output=T> + One + Sin + Cos
+    {
+        let half_angle = angle / (T::one() + T::one());
+        let s = half_angle.sin();
+        Quaternion {
+            w: half_angle.cos(),
+            x: axis.x * s,
+            y: axis.y * s,
+            z: axis.z * s,
+        }
+    }
+
+    /// Builds an orientation from yaw (about +y), then pitch (about +x), then roll (about +z),
+    /// all in radians. With all three at zero the orientation looks down -z with +y up.
+    pub fn from_yaw_pitch_roll(yaw: T, pitch: T, roll: T) -> Quaternion<T>
+    where
+        T: Add<Output=T> + Sub<Output=T> + Mul<Output=T> + Div<Output=T> + Zero + One + Sin + Cos
+    {
+        let qy = Self::from_axis_angle(Vec3::new(T::zero(), T::one(), T::zero()), yaw);
+        let qx = Self::from_axis_angle(Vec3::new(T::one(), T::zero(), T::zero()), pitch);
+        let qz = Self::from_axis_angle(Vec3::new(T::zero(), T::zero(), T::one()), roll);
+        qy * qx * qz
+    }
+
     pub fn from_uv(u: Vec3<T>, v: Vec3<T>) -> Quaternion<T>
     where
         T: Add<Output=T> + Sub<Output=T> + Mul<Output=T> + Div<Output=T> + Sqrt<Output=T> + PartialOrd + Zero + One
@@ -154,12 +180,12 @@ impl<T: Copy> Quaternion<T> {
         if allow_flip && (cos_angle < T::zero()) {
             c1 = -c1;
         }
-        return Quaternion {
+        Quaternion {
             w: c1 * a.w + c2 * b.w,
             x: c1 * a.x + c2 * b.x,
             y: c1 * a.y + c2 * b.y,
             z: c1 * a.z + c2 * b.z,
-        };
+        }
     }
 }
 
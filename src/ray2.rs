@@ -19,7 +19,7 @@ impl<T> Ray2<T> {
     where
         T: Add<Output=T> + Mul<Output=T> + Clone + Copy,
     {
-        return self.origin + self.direction * t;
+        self.origin + self.direction * t
     }
 }
 
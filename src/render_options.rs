@@ -0,0 +1,27 @@
+use crate::{FogConfig, HeightFilter, LightingConfig, ShadowMap, SkyConfig, Sprites, Traversal, WaterConfig};
+
+#[derive(Default)]
+pub struct RenderOptions {
+    /// When set, the terrain height at each ray hit is resampled at the exact hit point with
+    /// this filter instead of using the flat height of the whole cell.
+    pub height_filter: Option<HeightFilter>,
+    /// Distance fog applied per span of terrain; the background is cleared to the fog colour.
+    pub fog: Option<FogConfig>,
+    /// Per-cell Lambert shading from the cached terrain normals.
+    pub lighting: Option<LightingConfig>,
+    /// Cast shadows; must have been built from the height map being rendered.
+    pub shadow_map: Option<ShadowMap>,
+    /// Sky drawn above the terrain in every column instead of a flat background. The sun disc
+    /// follows `lighting`.
+    pub sky: Option<SkyConfig>,
+    /// Water over every cell below sea level. Reflects the sky when `sky` is set.
+    pub water: Option<WaterConfig>,
+    /// How each column's ray walks the height map. Pyramid nodes hidden below the terrain
+    /// already drawn are skipped, except by `Traversal::FlatDda`.
+    pub traversal: Traversal,
+    /// Walk far terrain at coarser, averaged levels of the pyramid once a node is at most this
+    /// many pixels wide; see `Camera::pixel_error`. Zero renders everything at full resolution.
+    pub pixel_error: f64,
+    /// Billboards drawn after the terrain, hidden wherever the terrain is in front of them.
+    pub sprites: Sprites,
+}
@@ -0,0 +1,198 @@
+use crate::height_map::scale_height;
+use crate::{HeightMap, Vec3, BLOCK_SIZE};
+
+/// Height added to each cell before marching towards the sun, so cells do not shadow themselves.
+const SHADOW_BIAS: f64 = 1.0;
+
+/// Per-cell sun visibility for the finest level of a `HeightMap`, found by marching from each
+/// cell towards the sun. The max-height pyramid lets the march skip whole quadtree nodes the
+/// ray already clears.
+///
+/// Moving the sun does not recompute anything by itself: `set_sun_direction` marks every row
+/// stale and `update` then recomputes a bounded number of rows per call, so the work can be
+/// spread across frames while the old shadows stay visible. Edits to the height map are picked
+/// up the same way: `update` notices the map's revision has changed and starts over.
+pub struct ShadowMap {
+    size: usize,
+    sun_direction: Vec3<f64>,
+    /// Light factor of shadowed cells; lit cells are 1.0.
+    shadow_light: f32,
+    light: Vec<f32>,
+    next_row: usize,
+    /// `HeightMap::id` and `HeightMap::revision` of the map the rows are being computed from.
+    map_id: u64,
+    revision: u64,
+}
+
+impl ShadowMap {
+    /// Computes every row straight away. `sun_direction` points from the terrain to the sun.
+    pub fn new(height_map: &HeightMap, sun_direction: Vec3<f64>, shadow_light: f32) -> ShadowMap {
+        let size = height_map.size();
+        let mut r = ShadowMap {
+            size,
+            sun_direction: sun_direction.normalize(),
+            shadow_light,
+            light: vec![1.0; size * size],
+            next_row: 0,
+            map_id: height_map.id(),
+            revision: height_map.revision(),
+        };
+        r.update(height_map, size);
+        r
+    }
+
+    pub fn sun_direction(&self) -> Vec3<f64> {
+        self.sun_direction
+    }
+
+    /// Changes the sun and marks every row stale; call `update` to recompute them.
+    pub fn set_sun_direction(&mut self, sun_direction: Vec3<f64>) {
+        self.sun_direction = sun_direction.normalize();
+        self.next_row = 0;
+    }
+
+    /// Marks every row stale after the height map has been edited.
+    pub fn invalidate(&mut self) {
+        self.next_row = 0;
+    }
+
+    /// Whether the shadows were built for `height_map`. Shadows built for a different map are
+    /// ignored by the renderer until `update` restarts them for this one.
+    pub fn fits(&self, height_map: &HeightMap) -> bool {
+        height_map.id() == self.map_id && height_map.size() == self.size
+    }
+
+    /// True once every row reflects the current sun direction and the height map as of the
+    /// last `update`.
+    pub fn is_complete(&self) -> bool {
+        self.next_row >= self.size
+    }
+
+    /// Recomputes up to `max_rows` stale rows and returns whether the map is now complete.
+    /// Every row is stale again if `height_map` has been edited since the last call, or if it
+    /// is not the map the shadows were built for.
+    pub fn update(&mut self, height_map: &HeightMap, max_rows: usize) -> bool {
+        if !self.fits(height_map) {
+            self.size = height_map.size();
+            self.light = vec![1.0; self.size * self.size];
+            self.map_id = height_map.id();
+            self.revision = height_map.revision();
+            self.next_row = 0;
+        }
+        if height_map.revision() != self.revision {
+            self.revision = height_map.revision();
+            self.next_row = 0;
+        }
+        let end_row = self.next_row.saturating_add(max_rows).min(self.size);
+        for z in self.next_row..end_row {
+            for x in 0..self.size {
+                let lit = self.is_lit(height_map, x, z);
+                self.light[z * self.size + x] = if lit { 1.0 } else { self.shadow_light };
+            }
+        }
+        self.next_row = end_row;
+        self.is_complete()
+    }
+
+    /// Light factor of a finest-level cell, to be multiplied into its colour.
+    pub fn light(&self, x: usize, z: usize) -> f32 {
+        self.light[z * self.size + x]
+    }
+
+    fn is_lit(&self, height_map: &HeightMap, x: usize, z: usize) -> bool {
+        let sun = self.sun_direction;
+        if sun.y <= 0.0 {
+            return false;
+        }
+        let horizontal = (sun.x * sun.x + sun.z * sun.z).sqrt();
+        if horizontal < 1.0e-9 {
+            return true;
+        }
+        let fine = height_map.num_levels() - 1;
+        // March in cell units from the centre of the cell; height rises by `rise` per cell.
+        let dir_x = sun.x / horizontal;
+        let dir_z = sun.z / horizontal;
+        let rise = sun.y / horizontal * BLOCK_SIZE;
+        let origin_x = x as f64 + 0.5;
+        let origin_z = z as f64 + 0.5;
+        let origin_h = scale_height(height_map.read(fine, x, z)) + SHADOW_BIAS;
+        let size = self.size as f64;
+        let mut s = 0.0;
+        loop {
+            let px = origin_x + dir_x * s;
+            let pz = origin_z + dir_z * s;
+            if px < 0.0 || pz < 0.0 || px >= size || pz >= size {
+                return true;
+            }
+            let ray_h = origin_h + rise * s;
+            // Find the coarsest node around the current point whose max height the ray clears.
+            let mut skipped = false;
+            for level in 0..=fine {
+                let shift = fine - level;
+                let node_x = (px as usize) >> shift;
+                let node_z = (pz as usize) >> shift;
+                if scale_height(height_map.read(level, node_x, node_z)) <= ray_h {
+                    let node_size = (1usize << shift) as f64;
+                    s += node_exit(px, pz, dir_x, dir_z, node_x as f64 * node_size, node_z as f64 * node_size, node_size);
+                    skipped = true;
+                    break;
+                }
+            }
+            if !skipped {
+                return false;
+            }
+        }
+    }
+}
+
+/// Distance along (`dir_x`, `dir_z`) from (`px`, `pz`) to just past the far side of the square
+/// node at (`x0`, `z0`) with side `node_size`.
+fn node_exit(px: f64, pz: f64, dir_x: f64, dir_z: f64, x0: f64, z0: f64, node_size: f64) -> f64 {
+    let mut exit = f64::INFINITY;
+    if dir_x > 0.0 {
+        exit = exit.min((x0 + node_size - px) / dir_x);
+    } else if dir_x < 0.0 {
+        exit = exit.min((x0 - px) / dir_x);
+    }
+    if dir_z > 0.0 {
+        exit = exit.min((z0 + node_size - pz) / dir_z);
+    } else if dir_z < 0.0 {
+        exit = exit.min((z0 - pz) / dir_z);
+    }
+    exit.max(0.0) + 1.0e-6
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A flat map with a tall wall across column `wall_x`.
+    fn walled_map(wall_x: usize) -> HeightMap {
+        let size = 16;
+        let heights: Vec<f64> = (0..size * size).map(|i| if i % size == wall_x { 1.0 } else { 0.0 }).collect();
+        HeightMap::from_grid(size, &heights, None)
+    }
+
+    fn lights(shadow_map: &ShadowMap) -> Vec<f32> {
+        (0..16 * 16).map(|i| shadow_map.light(i % 16, i / 16)).collect()
+    }
+
+    #[test]
+    fn restarts_for_another_map_of_the_same_size() {
+        let sun = Vec3::new(1.0, 0.3, 0.0);
+        let a = walled_map(12);
+        let b = walled_map(4);
+        let mut shadow_map = ShadowMap::new(&a, sun, 0.5);
+        assert!(shadow_map.fits(&a));
+        assert!(!shadow_map.fits(&b));
+
+        assert!(!shadow_map.update(&b, 4));
+        assert!(shadow_map.fits(&b));
+        assert!(shadow_map.update(&b, usize::MAX));
+        assert_eq!(lights(&shadow_map), lights(&ShadowMap::new(&b, sun, 0.5)));
+        assert_ne!(lights(&shadow_map), lights(&ShadowMap::new(&a, sun, 0.5)));
+
+        shadow_map.update(&a, usize::MAX);
+        assert_eq!(lights(&shadow_map), lights(&ShadowMap::new(&a, sun, 0.5)));
+    }
+}
@@ -0,0 +1,103 @@
+use noise::{Fbm, MultiFractal, NoiseFn, Perlin};
+
+use crate::colour::{lerp_rgb, pack_rgb};
+use crate::Vec3;
+
+pub struct SkyConfig {
+    pub zenith_colour: [u8; 3],
+    pub horizon_colour: [u8; 3],
+    /// Drawn around the lighting sun direction; no disc is drawn without lighting.
+    pub sun_colour: [u8; 3],
+    /// Angular radius of the sun disc in degrees.
+    pub sun_size: f64,
+    pub clouds: Option<CloudConfig>,
+}
+
+pub struct CloudConfig {
+    pub seed: u32,
+    /// Height of the flat cloud layer above the camera, in world units.
+    pub altitude: f64,
+    /// World units per noise period; larger values give bigger clouds.
+    pub scale: f64,
+    /// Fraction of the sky covered, in `0.0..=1.0`.
+    pub coverage: f64,
+    pub colour: [u8; 3],
+}
+
+impl Default for SkyConfig {
+    fn default() -> Self {
+        SkyConfig {
+            zenith_colour: [40, 90, 170],
+            horizon_colour: [170, 200, 230],
+            sun_colour: [255, 245, 220],
+            sun_size: 1.5,
+            clouds: None,
+        }
+    }
+}
+
+impl Default for CloudConfig {
+    fn default() -> Self {
+        CloudConfig {
+            seed: 0,
+            altitude: 3000.0,
+            scale: 8000.0,
+            coverage: 0.4,
+            colour: [250, 250, 250],
+        }
+    }
+}
+
+/// Per-frame state for shading sky pixels, so the cloud noise is only built once.
+pub(crate) struct SkyShader<'a> {
+    config: &'a SkyConfig,
+    sun_direction: Option<Vec3<f64>>,
+    camera_pos: Vec3<f64>,
+    clouds_noise: Option<Fbm<Perlin>>,
+}
+
+impl<'a> SkyShader<'a> {
+    pub(crate) fn new(config: &'a SkyConfig, sun_direction: Option<Vec3<f64>>, camera_pos: Vec3<f64>) -> SkyShader<'a> {
+        let clouds_noise = config.clouds.as_ref().map(|clouds| Fbm::<Perlin>::new(clouds.seed).set_octaves(4));
+        SkyShader {
+            config,
+            sun_direction,
+            camera_pos,
+            clouds_noise,
+        }
+    }
+
+    /// Colour seen along the unit world-space view direction `dir`.
+    pub(crate) fn colour(&self, dir: Vec3<f64>) -> u32 {
+        pack_rgb(self.rgb(dir))
+    }
+
+    pub(crate) fn rgb(&self, dir: Vec3<f64>) -> [u8; 3] {
+        let elevation = dir.y.clamp(0.0, 1.0);
+        let mut rgb = lerp_rgb(self.config.horizon_colour, self.config.zenith_colour, elevation.sqrt());
+        if let Some(sun) = self.sun_direction {
+            let cos_angle = dir.dot(sun);
+            let cos_radius = self.config.sun_size.to_radians().cos();
+            if cos_angle >= cos_radius {
+                rgb = self.config.sun_colour;
+            } else {
+                // Soft glow falling off away from the disc.
+                let glow = cos_angle.max(0.0).powf(64.0) * 0.5;
+                rgb = lerp_rgb(rgb, self.config.sun_colour, glow);
+            }
+        }
+        if let (Some(clouds), Some(noise)) = (&self.config.clouds, &self.clouds_noise) {
+            if dir.y > 0.0 {
+                let s = clouds.altitude / dir.y;
+                let x = (self.camera_pos.x + dir.x * s) / clouds.scale;
+                let z = (self.camera_pos.z + dir.z * s) / clouds.scale;
+                // Fbm output is roughly -1..1; shift it so `coverage` of it lands above zero.
+                let density = (noise.get([x, z]) * 0.5 + 0.5 - (1.0 - clouds.coverage)) * 4.0;
+                // Fade clouds out towards the horizon where the layer gets very thin on screen.
+                let fade = (dir.y * 8.0).min(1.0);
+                rgb = lerp_rgb(rgb, clouds.colour, density.clamp(0.0, 1.0) * fade);
+            }
+        }
+        rgb
+    }
+}
@@ -0,0 +1,150 @@
+use std::ops::Range;
+use crate::colour::{lerp_rgb, pack_rgb, unpack_rgb};
+use crate::{Camera, FogConfig, Vec3};
+
+/// Closest distance in front of the camera at which sprites are still drawn.
+const NEAR_PLANE: f64 = 1.0;
+
+/// An RGBA image shared by any number of sprites.
+pub struct SpriteImage {
+    width: usize,
+    height: usize,
+    /// Row-major from the top, in the framebuffer's `0xAABBGGRR` layout.
+    pixels: Vec<u32>,
+}
+
+impl SpriteImage {
+    /// Builds an image from tightly packed RGBA bytes, rows from the top. Panics on an empty
+    /// image or if `rgba` is not exactly `width * height * 4` bytes.
+    pub fn from_rgba(width: usize, height: usize, rgba: &[u8]) -> SpriteImage {
+        assert!(width > 0 && height > 0, "sprite images must not be empty, got {}x{}", width, height);
+        assert_eq!(
+            width.checked_mul(height).and_then(|n| n.checked_mul(4)),
+            Some(rgba.len()),
+            "RGBA data does not match a {}x{} image",
+            width,
+            height
+        );
+        let pixels = rgba
+            .chunks_exact(4)
+            .map(|p| u32::from_le_bytes([p[0], p[1], p[2], p[3]]))
+            .collect();
+        SpriteImage { width, height, pixels }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn texel(&self, u: usize, v: usize) -> u32 {
+        self.pixels[v * self.width + u]
+    }
+}
+
+/// A camera-facing billboard standing on `position`.
+pub struct Sprite {
+    /// World-space point under the bottom centre of the image.
+    pub position: Vec3<f64>,
+    /// World-space height of the image; the width follows the image's aspect ratio.
+    pub size: f64,
+    /// Index returned by `Sprites::add_image`.
+    pub image: usize,
+}
+
+/// Sprites drawn over the terrain, occluded by it per pixel.
+#[derive(Default)]
+pub struct Sprites {
+    images: Vec<SpriteImage>,
+    pub sprites: Vec<Sprite>,
+}
+
+impl Sprites {
+    /// Adds an image for sprites to use and returns its index.
+    pub fn add_image(&mut self, image: SpriteImage) -> usize {
+        self.images.push(image);
+        self.images.len() - 1
+    }
+
+    pub fn num_images(&self) -> usize {
+        self.images.len()
+    }
+
+    /// Adds a sprite and returns its index in `sprites`.
+    pub fn add(&mut self, sprite: Sprite) -> usize {
+        assert!(sprite.image < self.images.len(), "sprite image {} has not been added", sprite.image);
+        self.sprites.push(sprite);
+        self.sprites.len() - 1
+    }
+
+    /// Removes every sprite but keeps the images.
+    pub fn clear(&mut self) {
+        self.sprites.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sprites.is_empty()
+    }
+
+    /// Draws the sprites back to front into screen columns `columns` of an upright camera's
+    /// column-major `buffer` and `depth`, each `height` pixels tall. A sprite pixel is drawn
+    /// only where the sprite is nearer than what is already there, and mostly opaque pixels
+    /// write their distance to `depth`.
+    pub(crate) fn draw(&self, camera: &Camera<f64>, fog: Option<&FogConfig>, columns: Range<usize>, height: usize, buffer: &mut [u32], depth: &mut [f32]) {
+        let origin = camera.space.origin;
+        let mut visible: Vec<(f64, &Sprite)> = self
+            .sprites
+            .iter()
+            .filter(|sprite| -camera.space.point_to_space(sprite.position).z >= NEAR_PLANE)
+            .map(|sprite| {
+                ((sprite.position - origin).length(), sprite)
+            })
+            .collect();
+        visible.sort_by(|a, b| b.0.total_cmp(&a.0));
+        for (distance, sprite) in visible {
+            let image = &self.images[sprite.image];
+            // An upright camera keeps vertical lines vertical, so the sprite projects to an
+            // axis-aligned rectangle.
+            let base = camera.project(sprite.position);
+            let top = camera.project_y(Vec3::new(sprite.position.x, sprite.position.y + sprite.size, sprite.position.z));
+            let pixel_height = base.y - top;
+            if pixel_height <= 0.0 || image.width == 0 || image.height == 0 {
+                continue;
+            }
+            let pixel_width = pixel_height * image.width as f64 / image.height as f64;
+            let left = base.x - 0.5 * pixel_width;
+            let x_start = ((left - 0.5).ceil().max(columns.start as f64)) as usize;
+            let x_end = ((left + pixel_width - 0.5).ceil().min(columns.end as f64)).max(x_start as f64) as usize;
+            let y_start = (top - 0.5).ceil().max(0.0) as usize;
+            let y_end = ((base.y - 0.5).ceil().min(height as f64)).max(y_start as f64) as usize;
+            let fog_t = (sprite.position.x - origin.x).hypot(sprite.position.z - origin.z);
+            for x in x_start..x_end {
+                let u = (((x as f64 + 0.5 - left) / pixel_width * image.width as f64) as usize).min(image.width - 1);
+                let offset = (x - columns.start) * height;
+                for y in y_start..y_end {
+                    let index = offset + y;
+                    if distance as f32 >= depth[index] {
+                        continue;
+                    }
+                    let v = (((y as f64 + 0.5 - top) / pixel_height * image.height as f64) as usize).min(image.height - 1);
+                    let texel = image.texel(u, v);
+                    let alpha = (texel >> 24) as u8;
+                    if alpha == 0 {
+                        continue;
+                    }
+                    let mut rgb = unpack_rgb(texel);
+                    if let Some(fog) = fog {
+                        rgb = fog.apply(rgb, fog_t, sprite.position.y);
+                    }
+                    buffer[index] = pack_rgb(lerp_rgb(unpack_rgb(buffer[index]), rgb, alpha as f64 / 255.0));
+                    if alpha >= 128 {
+                        depth[index] = distance as f32;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -8,7 +8,7 @@ impl Sqrt for f32 {
     type Output = f32;
 
     fn sqrt(self) -> Self::Output {
-        return f32::sqrt(self);
+        f32::sqrt(self)
     }
 }
 
@@ -16,6 +16,6 @@ impl Sqrt for f64 {
     type Output = f64;
 
     fn sqrt(self) -> Self::Output {
-        return f64::sqrt(self);
+        f64::sqrt(self)
     }
 }
@@ -43,3 +43,11 @@ impl<T: Copy> Transform3<T> {
         self.orientation.conjugate().rotate(v)
     }
 }
+
+impl<T: Clone> Clone for Transform3<T> {
+    fn clone(&self) -> Self {
+        Self { origin: self.origin.clone(), orientation: self.orientation.clone() }
+    }
+}
+
+impl<T: Copy> Copy for Transform3<T> {}
@@ -0,0 +1,396 @@
+use crate::height_map::scale_height;
+use crate::{HeightMap, Ray2, BLOCK_SIZE, HEIGHT_SCALE};
+
+pub struct TimeHeight {
+    /// Distance along the ray where it enters the cell or node.
+    pub t: f64,
+    /// Distance along the ray where it leaves the cell or node.
+    pub t_exit: f64,
+    pub height: f64,
+    /// World-space height before negative heights are flattened to zero, i.e. the sea floor.
+    pub raw_height: f64,
+    /// Finest-level cell that was hit; for early bail tests, the node at its own level, and for
+    /// level-of-detail leaves, the finest cell at the node's centre.
+    pub cell_x: usize,
+    pub cell_z: usize,
+    /// Pyramid level of the cell or node; coarser than the finest level only for early bail
+    /// tests and level-of-detail leaves.
+    pub level: usize,
+}
+
+/// Walks far terrain at coarser pyramid levels: a node is visited as one cell, with the mean
+/// height of the cells under it, once it is at most `pixel_error` pixels wide where the ray
+/// enters it.
+#[derive(Clone, Copy, Debug)]
+pub struct LevelOfDetail {
+    pub pixel_error: f64,
+    /// Distance from the eye to the screen in pixels, as in `Camera::screen_dist`.
+    pub screen_dist: f64,
+}
+
+impl LevelOfDetail {
+    /// Coarsest level at or above `fine` to visit as a leaf at distance `t`.
+    pub fn level(&self, fine: usize, t: f64) -> usize {
+        // Widest node, in finest cells, that still fits in the pixel error.
+        let cells = self.pixel_error * t / (BLOCK_SIZE * self.screen_dist);
+        if cells.is_nan() || cells < 2.0 {
+            return fine;
+        }
+        fine - (cells.log2() as usize).min(fine)
+    }
+}
+
+/// A front-to-back walk of the cells under a horizontal ray.
+///
+/// `callback` gets every finest-level cell the ray crosses for a non-zero distance, in order,
+/// with `early_bail_test` false, except the cell the walk starts in. Before entering a coarser
+/// node of the max-height pyramid it may be called with `early_bail_test` true and the node's
+/// max height over `t..t_exit`; returning true skips the whole node. Every traversal visits
+/// the same cells with the same distances, so they are interchangeable.
+///
+/// With a `LevelOfDetail`, nodes far enough away are passed to `callback` as single cells,
+/// with `early_bail_test` false, instead of being walked into.
+pub trait RayTraversal {
+    fn walk_lod<Callback: FnMut(TimeHeight, bool, Option<[u8; 4]>) -> bool>(&self, height_map: &HeightMap, ray_xz: Ray2<f64>, lod: Option<LevelOfDetail>, callback: Callback);
+
+    fn walk<Callback: FnMut(TimeHeight, bool, Option<[u8; 4]>) -> bool>(&self, height_map: &HeightMap, ray_xz: Ray2<f64>, callback: Callback) {
+        self.walk_lod(height_map, ray_xz, None, callback);
+    }
+}
+
+/// Steps from cell to cell across the face the ray leaves through.
+pub struct DdaTraversal {
+    /// Climb the pyramid and make early bail tests; false walks every finest cell and ignores
+    /// any level of detail.
+    pub hierarchical: bool,
+}
+
+/// Recurses from the root into the children each node's ray segment crosses, nearest first.
+pub struct QuadTreeTraversal;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Traversal {
+    /// Hierarchical DDA.
+    #[default]
+    Dda,
+    /// DDA over the finest level only, without early bail tests or level of detail. For
+    /// comparison.
+    FlatDda,
+    QuadTree,
+}
+
+impl RayTraversal for Traversal {
+    fn walk_lod<Callback: FnMut(TimeHeight, bool, Option<[u8; 4]>) -> bool>(&self, height_map: &HeightMap, ray_xz: Ray2<f64>, lod: Option<LevelOfDetail>, callback: Callback) {
+        match self {
+            Traversal::Dda => DdaTraversal { hierarchical: true }.walk_lod(height_map, ray_xz, lod, callback),
+            Traversal::FlatDda => DdaTraversal { hierarchical: false }.walk_lod(height_map, ray_xz, lod, callback),
+            Traversal::QuadTree => QuadTreeTraversal.walk_lod(height_map, ray_xz, lod, callback),
+        }
+    }
+}
+
+/// The ray in finest-cell units with the map corner at the origin. All crossing distances
+/// come from the same expressions, so different walks agree on them exactly.
+struct GridRay {
+    origin_x: f64,
+    origin_z: f64,
+    dir_x: f64,
+    dir_z: f64,
+    /// Distance at which the walk starts: where the ray enters the map, or 0 inside it.
+    t_start: f64,
+    start_x: i64,
+    start_z: i64,
+}
+
+impl GridRay {
+    fn new(height_map: &HeightMap, ray_xz: Ray2<f64>) -> Option<GridRay> {
+        let size = height_map.size() as i64;
+        let half_size2 = 0.5 * (size as f64) * BLOCK_SIZE;
+        let mut r = GridRay {
+            origin_x: (ray_xz.origin.x + half_size2) / BLOCK_SIZE,
+            origin_z: (ray_xz.origin.y + half_size2) / BLOCK_SIZE,
+            dir_x: ray_xz.direction.x / BLOCK_SIZE,
+            dir_z: ray_xz.direction.y / BLOCK_SIZE,
+            t_start: 0.0,
+            start_x: 0,
+            start_z: 0,
+        };
+        let (t_entry, t_exit) = r.span(0, size, 0, size);
+        if t_exit <= t_entry {
+            return None;
+        }
+        r.t_start = t_entry;
+        r.start_x = cell_at(r.origin_x, r.dir_x, t_entry, 0, size);
+        r.start_z = cell_at(r.origin_z, r.dir_z, t_entry, 0, size);
+        Some(r)
+    }
+
+    /// Entry and exit distances of the ray through cells `x0..x1` by `z0..z1`, with the entry
+    /// no earlier than the start of the walk. Misses give an exit no later than the entry.
+    fn span(&self, x0: i64, x1: i64, z0: i64, z1: i64) -> (f64, f64) {
+        let (entry_x, exit_x) = slab(self.origin_x, self.dir_x, x0, x1);
+        let (entry_z, exit_z) = slab(self.origin_z, self.dir_z, z0, z1);
+        (entry_x.max(entry_z).max(self.t_start), exit_x.min(exit_z))
+    }
+}
+
+/// Entry and exit distances of a ray with `origin` and `dir` along one axis through `lo..hi`.
+fn slab(origin: f64, dir: f64, lo: i64, hi: i64) -> (f64, f64) {
+    if dir > 0.0 {
+        ((lo as f64 - origin) / dir, (hi as f64 - origin) / dir)
+    } else if dir < 0.0 {
+        ((hi as f64 - origin) / dir, (lo as f64 - origin) / dir)
+    } else if lo as f64 <= origin && origin < hi as f64 {
+        (f64::NEG_INFINITY, f64::INFINITY)
+    } else {
+        (f64::INFINITY, f64::NEG_INFINITY)
+    }
+}
+
+/// Cell in `lo..hi` along one axis that the ray occupies just after distance `t`, chosen with
+/// the same expressions as `slab` so it agrees with the spans other walks compute.
+fn cell_at(origin: f64, dir: f64, t: f64, lo: i64, hi: i64) -> i64 {
+    if hi - lo == 1 {
+        return lo;
+    }
+    let mut c = ((origin + dir * t).floor() as i64).clamp(lo, hi - 1);
+    let entry = |c: i64| slab(origin, dir, c, c + 1).0;
+    let exit = |c: i64| slab(origin, dir, c, c + 1).1;
+    if dir > 0.0 {
+        while c < hi - 1 && exit(c) <= t {
+            c += 1;
+        }
+        while c > lo && entry(c) > t {
+            c -= 1;
+        }
+    } else if dir < 0.0 {
+        while c > lo && exit(c) <= t {
+            c -= 1;
+        }
+        while c < hi - 1 && entry(c) > t {
+            c += 1;
+        }
+    }
+    c
+}
+
+fn fine_hit(height_map: &HeightMap, cell_x: i64, cell_z: i64, t: f64, t_exit: f64) -> (TimeHeight, Option<[u8; 4]>) {
+    let fine = height_map.num_levels()-1;
+    let height = height_map.read(fine, cell_x as usize, cell_z as usize);
+    let color = height_map.color_gradient().map(|color_gradient| color_gradient.get_color(height));
+    (node_hit(height, fine, cell_x, cell_z, t, t_exit), color)
+}
+
+/// A leaf of the walk: a finest cell, or a coarser node standing in for its cells.
+fn leaf_hit(height_map: &HeightMap, level: usize, node_x: i64, node_z: i64, t: f64, t_exit: f64) -> (TimeHeight, Option<[u8; 4]>) {
+    if level == height_map.num_levels()-1 {
+        fine_hit(height_map, node_x, node_z, t, t_exit)
+    } else {
+        lod_hit(height_map, level, node_x, node_z, t, t_exit)
+    }
+}
+
+/// A coarser node visited as one cell, at its mean height.
+fn lod_hit(height_map: &HeightMap, level: usize, node_x: i64, node_z: i64, t: f64, t_exit: f64) -> (TimeHeight, Option<[u8; 4]>) {
+    let height = height_map.read_mean(level, node_x as usize, node_z as usize);
+    let color = height_map.color_gradient().map(|color_gradient| color_gradient.get_color(height));
+    let shift = height_map.num_levels()-1 - level;
+    let centre = |node: i64| ((node << shift) + ((1 << shift) >> 1)) as usize;
+    let mut time_height = node_hit(height, level, node_x, node_z, t, t_exit);
+    time_height.cell_x = centre(node_x);
+    time_height.cell_z = centre(node_z);
+    (time_height, color)
+}
+
+fn node_hit(height: f64, level: usize, node_x: i64, node_z: i64, t: f64, t_exit: f64) -> TimeHeight {
+    TimeHeight {
+        t,
+        t_exit,
+        height: scale_height(height),
+        raw_height: height * HEIGHT_SCALE,
+        cell_x: node_x as usize,
+        cell_z: node_z as usize,
+        level,
+    }
+}
+
+impl RayTraversal for DdaTraversal {
+    fn walk_lod<Callback: FnMut(TimeHeight, bool, Option<[u8; 4]>) -> bool>(&self, height_map: &HeightMap, ray_xz: Ray2<f64>, lod: Option<LevelOfDetail>, mut callback: Callback) {
+        let ray = match GridRay::new(height_map, ray_xz) {
+            Some(ray) => ray,
+            None => return,
+        };
+        let fine = height_map.num_levels()-1;
+        let size = height_map.size() as i64;
+        let min_level = if self.hierarchical { 0 } else { fine };
+        let lod = lod.filter(|_| self.hierarchical);
+        let mut t = ray.t_start;
+        let mut cell_x = ray.start_x;
+        let mut cell_z = ray.start_z;
+        let mut prev_cell: Option<(i64, i64)> = None;
+        let mut level = min_level;
+        loop {
+            if cell_x < 0 || cell_z < 0 || cell_x >= size || cell_z >= size {
+                break;
+            }
+            // Climb back up while the parent node is one we have not tested yet.
+            if let Some((prev_x, prev_z)) = prev_cell {
+                while level > min_level {
+                    let parent_shift = fine - level + 1;
+                    if cell_x >> parent_shift == prev_x >> parent_shift && cell_z >> parent_shift == prev_z >> parent_shift {
+                        break;
+                    }
+                    level -= 1;
+                }
+            }
+            prev_cell = Some((cell_x, cell_z));
+            let shift = fine - level;
+            let node_x = cell_x >> shift;
+            let node_z = cell_z >> shift;
+            // First and one-past-last finest cells of the node on each axis.
+            let x0 = node_x << shift;
+            let x1 = (node_x + 1) << shift;
+            let z0 = node_z << shift;
+            let z1 = (node_z + 1) << shift;
+            let t_exit_x = slab(ray.origin_x, ray.dir_x, x0, x1).1;
+            let t_exit_z = slab(ray.origin_z, ray.dir_z, z0, z1).1;
+            let t_exit = t_exit_x.min(t_exit_z).max(t);
+            // Every node climbed into was entered at `t`, so this is the same choice the
+            // quadtree walk makes at the node's entry.
+            let leaf_level = lod.map_or(fine, |lod| lod.level(fine, t));
+            if level < leaf_level {
+                let height = height_map.read(level, node_x as usize, node_z as usize);
+                if !callback(node_hit(height, level, node_x, node_z, t, t_exit), true, None) {
+                    level += 1;
+                    continue;
+                }
+            } else if t_exit > t && (node_x, node_z) != (ray.start_x >> shift, ray.start_z >> shift) {
+                let (time_height, color) = leaf_hit(height_map, level, node_x, node_z, t, t_exit);
+                let _ = callback(time_height, false, color);
+            }
+            // Step into the neighbouring cell across the face the ray leaves through. The cell
+            // index along that axis is exact, so the walk always makes progress.
+            if t_exit_x < t_exit_z {
+                cell_x = if ray.dir_x > 0.0 { x1 } else { x0 - 1 };
+                cell_z = cell_at(ray.origin_z, ray.dir_z, t_exit, z0, z1);
+            } else {
+                cell_z = if ray.dir_z > 0.0 { z1 } else { z0 - 1 };
+                cell_x = cell_at(ray.origin_x, ray.dir_x, t_exit, x0, x1);
+            }
+            t = t_exit;
+        }
+    }
+}
+
+impl RayTraversal for QuadTreeTraversal {
+    fn walk_lod<Callback: FnMut(TimeHeight, bool, Option<[u8; 4]>) -> bool>(&self, height_map: &HeightMap, ray_xz: Ray2<f64>, lod: Option<LevelOfDetail>, mut callback: Callback) {
+        let ray = match GridRay::new(height_map, ray_xz) {
+            Some(ray) => ray,
+            None => return,
+        };
+        let size = height_map.size() as i64;
+        walk_node(height_map, &ray, lod, 0, 0, 0, ray.span(0, size, 0, size), &mut callback);
+    }
+}
+
+/// Visits the node at `level` whose ray segment is `span`, then its children nearest first.
+#[allow(clippy::too_many_arguments)]
+fn walk_node<Callback: FnMut(TimeHeight, bool, Option<[u8; 4]>) -> bool>(
+    height_map: &HeightMap,
+    ray: &GridRay,
+    lod: Option<LevelOfDetail>,
+    level: usize,
+    node_x: i64,
+    node_z: i64,
+    span: (f64, f64),
+    callback: &mut Callback,
+) {
+    let (t, t_exit) = span;
+    let fine = height_map.num_levels()-1;
+    let leaf_level = lod.map_or(fine, |lod| lod.level(fine, t));
+    if level >= leaf_level {
+        let shift = fine - level;
+        if (node_x, node_z) != (ray.start_x >> shift, ray.start_z >> shift) {
+            let (time_height, color) = leaf_hit(height_map, level, node_x, node_z, t, t_exit);
+            let _ = callback(time_height, false, color);
+        }
+        return;
+    }
+    let height = height_map.read(level, node_x as usize, node_z as usize);
+    if callback(node_hit(height, level, node_x, node_z, t, t_exit), true, None) {
+        return;
+    }
+    let shift = fine - level - 1;
+    let mut children = [((0.0, 0.0), 0, 0); 4];
+    let mut num_children = 0;
+    for (dx, dz) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+        let child_x = node_x * 2 + dx;
+        let child_z = node_z * 2 + dz;
+        let child_span = ray.span(child_x << shift, (child_x + 1) << shift, child_z << shift, (child_z + 1) << shift);
+        if child_span.0 < child_span.1 {
+            children[num_children] = (child_span, child_x, child_z);
+            num_children += 1;
+        }
+    }
+    let children = &mut children[..num_children];
+    children.sort_unstable_by(|a, b| a.0.0.total_cmp(&b.0.0));
+    for &mut (child_span, child_x, child_z) in children {
+        walk_node(height_map, ray, lod, level + 1, child_x, child_z, child_span, callback);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Camera, Quaternion, Transform3, Vec3, FOV_Y};
+
+    const WIDTH: usize = 160;
+    const HEIGHT: usize = 100;
+
+    fn height_map() -> HeightMap {
+        let size = 64;
+        let heights: Vec<f64> = (0..size * size)
+            .map(|i| ((i % size) as f64 * 0.31).sin() * ((i / size) as f64 * 0.17).cos())
+            .collect();
+        HeightMap::from_grid(size, &heights, None)
+    }
+
+    /// Every leaf `traversal` reports across the columns of a camera, with no early bails.
+    fn hits(height_map: &HeightMap, camera_space: Transform3<f64>, traversal: Traversal, pixel_error: f64) -> Vec<(usize, usize, usize, u64, u64)> {
+        let mut camera = Camera::new(camera_space, WIDTH as f64, HEIGHT as f64, FOV_Y);
+        camera.pixel_error = pixel_error;
+        let camera = camera.upright(WIDTH as f64, HEIGHT as f64);
+        let mut r = Vec::new();
+        for x in 0..WIDTH {
+            let Some(ray_xz) = camera.screen_x_to_ray_xz(x as f64) else {
+                continue;
+            };
+            traversal.walk_lod(height_map, ray_xz, camera.level_of_detail(), |hit, early_bail_test, _| {
+                if !early_bail_test {
+                    r.push((hit.level, hit.cell_x, hit.cell_z, hit.t.to_bits(), hit.t_exit.to_bits()));
+                }
+                false
+            });
+        }
+        r
+    }
+
+    #[test]
+    fn traversals_report_the_same_hits() {
+        let height_map = height_map();
+        let half = 0.5 * height_map.size() as f64 * BLOCK_SIZE;
+        let mut num_hits = 0;
+        for yaw in [0.0, 45.0, 90.0, 135.0, 180.0, -60.0, -90.0] {
+            for pos in [Vec3::new(0.0, 900.0, half * 0.9), Vec3::new(-half * 0.5, 1500.0, 0.0), Vec3::new(0.0, 600.0, half * 2.0)] {
+                let camera_space = Transform3::new(pos, Quaternion::from_yaw_pitch_roll(f64::to_radians(yaw), f64::to_radians(-10.0), 0.0));
+                let dda = hits(&height_map, camera_space, Traversal::Dda, 0.0);
+                num_hits += dda.len();
+                assert!(dda == hits(&height_map, camera_space, Traversal::FlatDda, 0.0), "flat DDA hits differ at yaw {}", yaw);
+                assert!(dda == hits(&height_map, camera_space, Traversal::QuadTree, 0.0), "quadtree hits differ at yaw {}", yaw);
+                let dda_lod = hits(&height_map, camera_space, Traversal::Dda, 2.0);
+                assert!(dda_lod == hits(&height_map, camera_space, Traversal::QuadTree, 2.0), "quadtree hits differ with level of detail at yaw {}", yaw);
+            }
+        }
+        assert!(num_hits > 0);
+    }
+}
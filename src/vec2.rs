@@ -25,7 +25,7 @@ impl<T: Copy> Vec2<T> {
     where
         T: Add<Output=T> + Mul<Output=T> + Clone
     {
-        self.x.clone() * self.x.clone() + self.y.clone() * self.y.clone()
+        self.x * self.x + self.y * self.y
     }
 
     pub fn length(&self) -> <<<T as Mul>::Output as Add>::Output as Sqrt>::Output
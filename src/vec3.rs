@@ -20,7 +20,7 @@ impl<T: Copy> Vec3<T> {
     where
         T: Add<Output=T> + Mul<Output=T>
     {
-        self.x * rhs.x + self.y * rhs.y
+        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
     }
 
     pub fn cross(self, rhs: Vec3<T>) -> Vec3<T>
@@ -38,7 +38,7 @@ impl<T: Copy> Vec3<T> {
     where
         T: Add<Output=T> + Mul<Output=T> + Clone
     {
-        self.x.clone() * self.x.clone() + self.y.clone() * self.y.clone() + self.z.clone() * self.z.clone()
+        self.x * self.x + self.y * self.y + self.z * self.z
     }
 
     pub fn length(&self) -> T
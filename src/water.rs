@@ -0,0 +1,62 @@
+use crate::colour::lerp_rgb;
+
+/// Reflectance of water seen straight down, for Schlick's approximation.
+const WATER_REFLECTANCE: f64 = 0.02;
+
+/// A flat water layer drawn over every cell whose true height is below `sea_level`.
+pub struct WaterConfig {
+    /// World-space height of the water surface.
+    pub sea_level: f64,
+    /// Colour of deep water.
+    pub colour: [u8; 3],
+    /// How quickly the sea floor fades into `colour` with depth: the floor shows through by
+    /// `e^(-absorption * depth)`.
+    pub absorption: f64,
+    /// Reflected at grazing angles when there is no sky to reflect.
+    pub reflection_colour: [u8; 3],
+    pub foam: Option<FoamConfig>,
+}
+
+pub struct FoamConfig {
+    /// Water shallower than this (in world units) gets foam, thickest at the shoreline.
+    pub depth: f64,
+    pub colour: [u8; 3],
+}
+
+impl Default for WaterConfig {
+    fn default() -> Self {
+        WaterConfig {
+            sea_level: 0.0,
+            colour: [20, 60, 110],
+            absorption: 0.02,
+            reflection_colour: [170, 200, 230],
+            foam: Some(FoamConfig::default()),
+        }
+    }
+}
+
+impl Default for FoamConfig {
+    fn default() -> Self {
+        FoamConfig {
+            depth: 8.0,
+            colour: [235, 245, 250],
+        }
+    }
+}
+
+impl WaterConfig {
+    /// Colour of the water surface over a floor of colour `floor` lying `depth` below it.
+    /// `cos_view` is the cosine between the view ray and the surface normal, and `reflection`
+    /// the colour reflected off the surface.
+    pub fn shade(&self, floor: [u8; 3], depth: f64, cos_view: f64, reflection: [u8; 3]) -> [u8; 3] {
+        let mut rgb = lerp_rgb(floor, self.colour, 1.0 - (-self.absorption * depth.max(0.0)).exp());
+        let fresnel = WATER_REFLECTANCE + (1.0 - WATER_REFLECTANCE) * (1.0 - cos_view.clamp(0.0, 1.0)).powi(5);
+        rgb = lerp_rgb(rgb, reflection, fresnel);
+        if let Some(foam) = &self.foam {
+            if depth < foam.depth {
+                rgb = lerp_rgb(rgb, foam.colour, 1.0 - depth / foam.depth);
+            }
+        }
+        rgb
+    }
+}
@@ -1,9 +1,21 @@
 import { defineConfig } from "vite";
 import wasm from "vite-plugin-wasm";
 
+// SharedArrayBuffer, used by the ?workers=N mode, needs a cross-origin isolated page.
+const crossOriginIsolation = {
+  "Cross-Origin-Opener-Policy": "same-origin",
+  "Cross-Origin-Embedder-Policy": "require-corp",
+};
+
 export default defineConfig({
   base: "",
   plugins: [
     wasm()
-  ]
+  ],
+  server: {
+    headers: crossOriginIsolation,
+  },
+  preview: {
+    headers: crossOriginIsolation,
+  },
 });